@@ -0,0 +1,110 @@
+/// Where an extracted document comes from. It's written in the output as a
+/// `# pal: key=value; ...` comment right before the document, so it survives
+/// in the `.graphql` file without affecting parsing.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DocumentSource {
+    pub kind: Option<String>,
+    pub path: Option<String>,
+    /// Line of the source file where the document starts
+    pub line: Option<usize>,
+    pub crate_name: Option<String>,
+}
+
+const HEADER_PREFIX: &str = "# pal: ";
+
+impl DocumentSource {
+    pub fn header(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+
+        if let Some(kind) = &self.kind {
+            parts.push(format!("kind={}", kind));
+        }
+
+        if let Some(path) = &self.path {
+            parts.push(format!("path={}", path));
+        }
+
+        if let Some(line) = &self.line {
+            parts.push(format!("line={}", line));
+        }
+
+        if let Some(crate_name) = &self.crate_name {
+            parts.push(format!("crate={}", crate_name));
+        }
+
+        format!("{}{}", HEADER_PREFIX, parts.join("; "))
+    }
+
+    pub fn annotate(&self, document: &str) -> String {
+        format!("{}\n{}", self.header(), document)
+    }
+
+    pub fn from_header(line: &str) -> Option<Self> {
+        let mut source = DocumentSource::default();
+
+        for part in line.strip_prefix(HEADER_PREFIX)?.split("; ") {
+            let mut key_value = part.splitn(2, '=');
+
+            match (key_value.next(), key_value.next()) {
+                (Some("kind"), Some(value)) => source.kind = Some(value.to_string()),
+                (Some("path"), Some(value)) => source.path = Some(value.to_string()),
+                (Some("line"), Some(value)) => source.line = value.parse().ok(),
+                (Some("crate"), Some(value)) => source.crate_name = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        Some(source)
+    }
+}
+
+/// Splits an extracted document file back into the annotated documents,
+/// text before the first header gets an empty source.
+pub fn split_documents(content: &str) -> Vec<(DocumentSource, String)> {
+    let mut documents: Vec<(DocumentSource, String)> = Vec::new();
+    let mut current = (DocumentSource::default(), String::new());
+
+    for line in content.lines() {
+        match DocumentSource::from_header(line) {
+            Some(source) => {
+                let previous = std::mem::replace(&mut current, (source, String::new()));
+
+                if !previous.1.trim().is_empty() {
+                    documents.push(previous);
+                }
+            }
+            None => {
+                current.1.push_str(line);
+                current.1.push('\n');
+            }
+        }
+    }
+
+    if !current.1.trim().is_empty() {
+        documents.push(current);
+    }
+
+    documents
+}
+
+/// Splits an extracted document file, leaving out the documents extracted
+/// from sources of the given kinds.
+pub fn filter_kinds(content: &str, kinds: &[String]) -> Vec<(DocumentSource, String)> {
+    split_documents(content)
+        .into_iter()
+        .filter(|(source, _)| match &source.kind {
+            Some(kind) => !kinds.contains(kind),
+            None => true,
+        })
+        .collect()
+}
+
+/// Removes the documents extracted from sources of the given kinds, for
+/// example to leave documentation examples out of the stats.
+pub fn exclude_kinds(content: &str, kinds: &[String]) -> String {
+    filter_kinds(content, kinds)
+        .into_iter()
+        .map(|(_, document)| document)
+        .collect::<Vec<String>>()
+        .join("")
+}
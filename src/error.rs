@@ -0,0 +1,116 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum Error {
+    /// A file couldn't be read or written
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// One of the include or exclude patterns isn't a valid glob
+    InvalidPattern(String),
+    InvalidSchema(String),
+    /// JSON that isn't an introspection result
+    InvalidIntrospection(String),
+    /// A `Name: value` header that can't be parsed or uses an unset
+    /// environment variable
+    InvalidHeader(String),
+    /// The schema couldn't be fetched from an endpoint
+    Fetch {
+        url: String,
+        reason: String,
+    },
+    /// A schema file that isn't valid SDL
+    SchemaSyntax {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        reason: String,
+    },
+    InvalidDocuments(String),
+    /// A `Type.field` coordinate that can't be parsed
+    InvalidCoordinate(String),
+    /// A schema coordinate that isn't a type, a field or an argument
+    InvalidSchemaCoordinate(String),
+    UnknownType(String),
+    UnknownField {
+        type_name: String,
+        field: String,
+    },
+    /// Folded stacks inferno couldn't render
+    Flamegraph(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn io<P: Into<PathBuf>>(path: P, source: io::Error) -> Self {
+        Error::Io {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::InvalidPattern(reason) => write!(f, "Invalid pattern: {}", reason),
+            Error::InvalidSchema(reason) => write!(f, "Unable to parse schema: {}", reason),
+            Error::InvalidIntrospection(reason) => {
+                write!(f, "Unable to read introspection result: {}", reason)
+            }
+            Error::InvalidHeader(reason) => write!(f, "Invalid header {}", reason),
+            Error::Fetch { url, reason } => {
+                write!(f, "Unable to fetch the schema from {}: {}", url, reason)
+            }
+            Error::SchemaSyntax {
+                path,
+                line,
+                column,
+                reason,
+            } => {
+                if path.as_os_str().is_empty() {
+                    write!(f, "Invalid schema at {}:{}: {}", line, column, reason)
+                } else {
+                    write!(
+                        f,
+                        "Invalid schema at {}:{}:{}: {}",
+                        path.display(),
+                        line,
+                        column,
+                        reason
+                    )
+                }
+            }
+            Error::InvalidDocuments(reason) => write!(f, "Unable to parse documents: {}", reason),
+            Error::InvalidCoordinate(coordinate) => write!(
+                f,
+                "Invalid coordinate {}, expected the Type.field format",
+                coordinate
+            ),
+            Error::InvalidSchemaCoordinate(coordinate) => write!(
+                f,
+                "Invalid coordinate {}, expected Type, Type.field or Type.field(argument:)",
+                coordinate
+            ),
+            Error::UnknownType(name) => write!(f, "Unable to get type {}", name),
+            Error::UnknownField { type_name, field } => {
+                write!(f, "Unable to get {} in {}", field, type_name)
+            }
+            Error::Flamegraph(reason) => write!(f, "Unable to render the flame graph: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
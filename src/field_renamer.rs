@@ -0,0 +1,320 @@
+use crate::error::{Error, Result};
+use crate::query_extractor::{find_graphql_templates, SkipCategory, SkippedResult};
+use crate::schema_stats::{GraphQLType, Schema};
+use graphql_parser::query::parse_query;
+use graphql_parser::query::Definition::{Fragment, Operation};
+use graphql_parser::query::Selection::{Field, FragmentSpread, InlineFragment};
+use graphql_parser::query::SelectionSet;
+use graphql_parser::query::TypeCondition::On;
+use graphql_parser::Pos;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs::{read_to_string, write};
+use std::path::PathBuf;
+
+pub struct FieldRename {
+    pub type_name: String,
+    pub field_name: String,
+    pub new_name: String,
+    /// Adds an alias to the old name, so the response shape doesn't change
+    pub add_alias: bool,
+}
+
+pub struct RenamedField {
+    pub path: String,
+    pub line: usize,
+}
+
+pub struct RenameResult {
+    pub renamed: Vec<RenamedField>,
+    pub skipped_files: Vec<SkippedResult>,
+}
+
+struct Edit {
+    offset: usize,
+    length: usize,
+    text: String,
+}
+
+impl FieldRename {
+    /// Parses a `Type.field` coordinate.
+    pub fn from_coordinate(coordinate: &str, new_name: &str, add_alias: bool) -> Result<Self> {
+        let mut parts = coordinate.split('.');
+
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(type_name), Some(field_name), None)
+                if !type_name.is_empty() && !field_name.is_empty() =>
+            {
+                Ok(FieldRename {
+                    type_name: type_name.to_string(),
+                    field_name: field_name.to_string(),
+                    new_name: new_name.to_string(),
+                    add_alias,
+                })
+            }
+            _ => Err(Error::InvalidCoordinate(coordinate.to_string())),
+        }
+    }
+
+    /// Selections on types implementing the interface we are renaming a
+    /// field of are renamed too, as they have to keep matching the interface.
+    fn matches(&self, parent_type: &str, schema: &Schema) -> bool {
+        if parent_type == self.type_name {
+            return true;
+        }
+
+        match schema.types.get(parent_type) {
+            Some(t) => t.interfaces.contains(&self.type_name),
+            None => false,
+        }
+    }
+}
+
+/// Converts a graphql-parser position to a byte offset in the text, the
+/// parser counts tabs as 8 columns.
+fn offset_for_position(text: &str, position: Pos) -> Option<usize> {
+    let mut line_start = 0;
+
+    for _ in 1..position.line {
+        line_start += text[line_start..].find('\n')? + 1;
+    }
+
+    let mut column = 1;
+
+    for (index, c) in text[line_start..].char_indices() {
+        if column >= position.column {
+            return Some(line_start + index);
+        }
+
+        column += match c {
+            '\t' => 8,
+            '\n' => return None,
+            _ => 1,
+        };
+    }
+
+    None
+}
+
+fn find_name_offset(text: &str, offset: usize, alias: Option<&str>) -> Option<usize> {
+    match alias {
+        None => Some(offset),
+        Some(alias) => {
+            let rest = text[offset..].strip_prefix(alias)?;
+            let colon = rest.find(':')?;
+
+            if !rest[..colon].chars().all(|c| c.is_whitespace() || c == ',') {
+                return None;
+            }
+
+            let after_colon = &rest[colon + 1..];
+            let name_start = after_colon.len() - after_colon.trim_start().len();
+
+            Some(text.len() - after_colon.len() + name_start)
+        }
+    }
+}
+
+fn field_type_name(
+    schema: &HashMap<String, GraphQLType>,
+    type_name: &str,
+    field: &str,
+) -> Option<String> {
+    let field = schema.get(type_name)?.fields.get(field)?;
+
+    Some(
+        field
+            .r#type
+            .clone()
+            .replace("[", "")
+            .replace("]", "")
+            .replace("!", ""),
+    )
+}
+
+fn collect_edits<'a>(
+    selection_set: &SelectionSet<'a, &'a str>,
+    parent_type: &str,
+    text: &str,
+    rename: &FieldRename,
+    schema: &Schema,
+    edits: &mut Vec<Edit>,
+) -> std::result::Result<(), String> {
+    for item in &selection_set.items {
+        match item {
+            Field(f) => {
+                if f.name == rename.field_name && rename.matches(parent_type, schema) {
+                    let name_offset = offset_for_position(text, f.position)
+                        .and_then(|offset| find_name_offset(text, offset, f.alias))
+                        .filter(|offset| text[*offset..].starts_with(f.name))
+                        .ok_or(format!(
+                            "Unable to find {} at {}",
+                            rename.field_name, f.position
+                        ))?;
+
+                    let replacement = match (f.alias, rename.add_alias) {
+                        (None, true) => format!("{}: {}", rename.field_name, rename.new_name),
+                        _ => rename.new_name.clone(),
+                    };
+
+                    edits.push(Edit {
+                        offset: name_offset,
+                        length: f.name.len(),
+                        text: replacement,
+                    });
+                }
+
+                if let Some(field_type) = field_type_name(&schema.types, parent_type, f.name) {
+                    collect_edits(&f.selection_set, &field_type, text, rename, schema, edits)?;
+                }
+            }
+            InlineFragment(fragment) => {
+                let type_name = match &fragment.type_condition {
+                    Some(On(type_name)) => type_name,
+                    None => parent_type,
+                };
+
+                collect_edits(
+                    &fragment.selection_set,
+                    type_name,
+                    text,
+                    rename,
+                    schema,
+                    edits,
+                )?;
+            }
+            // fragments are renamed where they are defined
+            FragmentSpread(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn find_edits_in_document(
+    text: &str,
+    rename: &FieldRename,
+    schema: &Schema,
+) -> std::result::Result<Vec<Edit>, String> {
+    let document = parse_query::<&str>(text).map_err(|e| e.to_string())?;
+    let mut edits: Vec<Edit> = Vec::new();
+
+    for definition in &document.definitions {
+        match definition {
+            Operation(operation) => {
+                let (root_type, selection_set) = schema.roots.for_operation(operation)?;
+
+                collect_edits(selection_set, root_type, text, rename, schema, &mut edits)?;
+            }
+            Fragment(fragment) => {
+                let On(type_name) = &fragment.type_condition;
+
+                collect_edits(
+                    &fragment.selection_set,
+                    type_name,
+                    text,
+                    rename,
+                    schema,
+                    &mut edits,
+                )?;
+            }
+        }
+    }
+
+    Ok(edits)
+}
+
+/// Renames every selection of a field in the given `.graphql` and JS/TS
+/// files, using the schema to know the type each selection is made on.
+pub fn rename_field(
+    paths: &[PathBuf],
+    rename: &FieldRename,
+    schema: &Schema,
+    dry_run: bool,
+) -> Result<RenameResult> {
+    match schema.types.get(&rename.type_name) {
+        Some(t) if t.fields.contains_key(&rename.field_name) => {}
+        Some(_) => {
+            return Err(Error::UnknownField {
+                type_name: rename.type_name.clone(),
+                field: rename.field_name.clone(),
+            })
+        }
+        None => return Err(Error::UnknownType(rename.type_name.clone())),
+    }
+
+    let mut renamed: Vec<RenamedField> = Vec::new();
+    let mut skipped_files: Vec<SkippedResult> = Vec::new();
+
+    for path in paths {
+        let documents = if path.extension() == Some(OsStr::new("graphql")) {
+            read_to_string(path)
+                .map(|content| (content.clone(), vec![(0, content)]))
+                .map_err(|e| SkippedResult::new(path, SkipCategory::Io, e.to_string()))
+        } else {
+            find_graphql_templates(path).map(|(content, templates)| {
+                let documents = templates
+                    .into_iter()
+                    .map(|template| (template.offset, template.text))
+                    .collect();
+
+                (content, documents)
+            })
+        };
+
+        let (mut content, documents) = match documents {
+            Ok(result) => result,
+            Err(skipped) => {
+                skipped_files.push(skipped);
+
+                continue;
+            }
+        };
+
+        let mut edits: Vec<Edit> = Vec::new();
+
+        for (offset, text) in documents {
+            match find_edits_in_document(&text, rename, schema) {
+                Ok(document_edits) => edits.extend(document_edits.into_iter().map(|edit| Edit {
+                    offset: offset + edit.offset,
+                    ..edit
+                })),
+                Err(reason) => skipped_files.push(
+                    SkippedResult::new(path, SkipCategory::Parse, reason)
+                        .at_line(content[..offset].matches('\n').count() + 1),
+                ),
+            }
+        }
+
+        if edits.is_empty() {
+            continue;
+        }
+
+        for edit in &edits {
+            renamed.push(RenamedField {
+                path: path.display().to_string(),
+                line: content[..edit.offset].matches('\n').count() + 1,
+            });
+        }
+
+        if dry_run {
+            continue;
+        }
+
+        // apply edits from the end so the offsets stay valid
+        edits.sort_by_key(|edit| std::cmp::Reverse(edit.offset));
+
+        for edit in edits {
+            content.replace_range(edit.offset..edit.offset + edit.length, &edit.text);
+        }
+
+        if let Err(e) = write(path, content) {
+            skipped_files.push(SkippedResult::new(path, SkipCategory::Io, e.to_string()));
+        }
+    }
+
+    Ok(RenameResult {
+        renamed,
+        skipped_files,
+    })
+}
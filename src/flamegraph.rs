@@ -0,0 +1,40 @@
+use crate::error::{Error, Result};
+use crate::schema_stats::UsageNode;
+use inferno::flamegraph::{from_lines, Options};
+use std::collections::BTreeMap;
+
+fn fold(stack: &str, node: &UsageNode, lines: &mut Vec<String>) {
+    lines.push(format!("{} {}", stack, node.count));
+
+    for (field, child) in &node.children {
+        fold(&format!("{};{}", stack, field), child, lines);
+    }
+}
+
+/// Writes the usage tree in the folded stacks format read by inferno and
+/// flamegraph.pl, like `Query;viewer;repositories;name 42`. Every path
+/// gets its own count, so the width of a frame is the number of selections
+/// at and below it.
+pub fn folded_stacks(tree: &BTreeMap<String, UsageNode>) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for (root_type, root) in tree {
+        fold(root_type, root, &mut lines);
+    }
+
+    lines
+}
+
+/// Renders folded stacks as an SVG flame graph.
+pub fn flamegraph_svg(folded: &[String], title: &str) -> Result<Vec<u8>> {
+    let mut options = Options::default();
+    options.title = title.to_string();
+    options.count_name = "selections".to_string();
+
+    let mut svg: Vec<u8> = Vec::new();
+
+    from_lines(&mut options, folded.iter().map(String::as_str), &mut svg)
+        .map_err(|e| Error::Flamegraph(e.to_string()))?;
+
+    Ok(svg)
+}
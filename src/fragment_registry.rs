@@ -0,0 +1,102 @@
+use crate::document::split_documents;
+use crate::query_extractor::{SkipCategory, SkippedResult};
+use graphql_parser::query::parse_query;
+use graphql_parser::query::Definition::{Fragment, Operation};
+use graphql_parser::query::OperationDefinition;
+use graphql_parser::query::Selection::{Field, FragmentSpread, InlineFragment};
+use graphql_parser::query::SelectionSet;
+use std::collections::HashMap;
+
+fn collect_spreads<'a>(selection_set: &SelectionSet<'a, &'a str>, spreads: &mut Vec<String>) {
+    for item in &selection_set.items {
+        match item {
+            Field(f) => collect_spreads(&f.selection_set, spreads),
+            InlineFragment(fragment) => collect_spreads(&fragment.selection_set, spreads),
+            FragmentSpread(spread) => spreads.push(spread.fragment_name.to_string()),
+        }
+    }
+}
+
+/// Validates fragment spreads against all the fragments defined in the
+/// project, for setups like Gatsby where fragments are global and not
+/// imported. Nothing is inlined, the spreads are resolved by whatever reads
+/// the extracted documents.
+///
+/// `documents` are annotated documents, as produced by `extract-queries`.
+/// A fragment inlined in several documents through `${Fragment}` is the
+/// same definition every time, so only different definitions sharing a name
+/// are reported.
+pub fn check_fragment_spreads(documents: &[String]) -> Vec<SkippedResult> {
+    let mut skipped_files: Vec<SkippedResult> = Vec::new();
+    // fragment name to the path and text of its first definition
+    let mut registry: HashMap<String, (String, String)> = HashMap::new();
+    let mut usages: Vec<(String, Vec<String>)> = Vec::new();
+
+    for (source, document) in split_documents(&documents.join("\n")) {
+        let path = source.path.unwrap_or_default();
+
+        let ast = match parse_query::<&str>(&document) {
+            Ok(ast) => ast,
+            Err(e) => {
+                skipped_files.push(SkippedResult::new(path, SkipCategory::Parse, e.to_string()));
+
+                continue;
+            }
+        };
+
+        let mut spreads: Vec<String> = Vec::new();
+
+        for definition in &ast.definitions {
+            match definition {
+                Operation(operation) => match operation {
+                    OperationDefinition::Query(q) => {
+                        collect_spreads(&q.selection_set, &mut spreads)
+                    }
+                    OperationDefinition::Mutation(m) => {
+                        collect_spreads(&m.selection_set, &mut spreads)
+                    }
+                    OperationDefinition::Subscription(s) => {
+                        collect_spreads(&s.selection_set, &mut spreads)
+                    }
+                    OperationDefinition::SelectionSet(s) => collect_spreads(s, &mut spreads),
+                },
+                Fragment(fragment) => {
+                    collect_spreads(&fragment.selection_set, &mut spreads);
+
+                    let text = fragment.to_string();
+
+                    match registry.get(fragment.name) {
+                        Some((_, known)) if *known == text => {}
+                        Some((other, _)) => skipped_files.push(SkippedResult::new(
+                            &path,
+                            SkipCategory::Resolve,
+                            format!(
+                                "Fragment {} is defined more than once (also in {})",
+                                fragment.name, other
+                            ),
+                        )),
+                        None => {
+                            registry.insert(fragment.name.to_string(), (path.clone(), text));
+                        }
+                    }
+                }
+            }
+        }
+
+        usages.push((path, spreads));
+    }
+
+    for (path, spreads) in usages {
+        for spread in spreads {
+            if !registry.contains_key(&spread) {
+                skipped_files.push(SkippedResult::new(
+                    &path,
+                    SkipCategory::Resolve,
+                    format!("Unknown fragment {}", spread),
+                ));
+            }
+        }
+    }
+
+    skipped_files
+}
@@ -0,0 +1,373 @@
+use crate::error::{Error, Result};
+use crate::schema_stats::{BUILT_IN_DIRECTIVES, DEFAULT_DEPRECATION_REASON};
+use graphql_parser::schema::{
+    Definition, Directive, DirectiveDefinition, DirectiveLocation, Document, EnumType, EnumValue,
+    Field, InputObjectType, InputValue, InterfaceType, ObjectType, ScalarType, SchemaDefinition,
+    Type, TypeDefinition, UnionType, Value,
+};
+use graphql_parser::Pos;
+use serde::Deserialize;
+use serde_json::Value as Json;
+
+const BUILT_IN_SCALARS: &[&str] = &["String", "Int", "Float", "Boolean", "ID"];
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IntrospectionSchema {
+    query_type: Option<NamedTypeRef>,
+    mutation_type: Option<NamedTypeRef>,
+    subscription_type: Option<NamedTypeRef>,
+    types: Vec<FullType>,
+    #[serde(default)]
+    directives: Vec<IntrospectionDirective>,
+}
+
+#[derive(Deserialize)]
+struct NamedTypeRef {
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FullType {
+    kind: String,
+    name: String,
+    description: Option<String>,
+    fields: Option<Vec<IntrospectionField>>,
+    input_fields: Option<Vec<IntrospectionInputValue>>,
+    interfaces: Option<Vec<TypeRef>>,
+    enum_values: Option<Vec<IntrospectionEnumValue>>,
+    possible_types: Option<Vec<TypeRef>>,
+    #[serde(rename = "specifiedByUrl", alias = "specifiedByURL")]
+    specified_by_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IntrospectionField {
+    name: String,
+    description: Option<String>,
+    #[serde(default)]
+    args: Vec<IntrospectionInputValue>,
+    #[serde(rename = "type")]
+    type_ref: TypeRef,
+    #[serde(default)]
+    is_deprecated: bool,
+    deprecation_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IntrospectionInputValue {
+    name: String,
+    description: Option<String>,
+    #[serde(rename = "type")]
+    type_ref: TypeRef,
+    default_value: Option<String>,
+    #[serde(default)]
+    is_deprecated: bool,
+    deprecation_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IntrospectionEnumValue {
+    name: String,
+    description: Option<String>,
+    #[serde(default)]
+    is_deprecated: bool,
+    deprecation_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IntrospectionDirective {
+    name: String,
+    description: Option<String>,
+    locations: Vec<String>,
+    #[serde(default)]
+    args: Vec<IntrospectionInputValue>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TypeRef {
+    kind: String,
+    name: Option<String>,
+    of_type: Option<Box<TypeRef>>,
+}
+
+/// Accepts a full introspection response, its `data` or the bare
+/// `__schema` object.
+fn find_schema(mut json: Json) -> Result<IntrospectionSchema> {
+    if let Some(data) = json.get_mut("data") {
+        json = data.take();
+    }
+
+    if let Some(schema) = json.get_mut("__schema") {
+        json = schema.take();
+    }
+
+    serde_json::from_value(json).map_err(|e| Error::InvalidIntrospection(e.to_string()))
+}
+
+fn to_type(type_ref: &TypeRef) -> Result<Type<'static, String>> {
+    let of_type = || {
+        type_ref
+            .of_type
+            .as_deref()
+            .ok_or_else(|| Error::InvalidIntrospection(format!("{} without ofType", type_ref.kind)))
+    };
+
+    match type_ref.kind.as_str() {
+        "NON_NULL" => Ok(Type::NonNullType(Box::new(to_type(of_type()?)?))),
+        "LIST" => Ok(Type::ListType(Box::new(to_type(of_type()?)?))),
+        _ => match &type_ref.name {
+            Some(name) => Ok(Type::NamedType(name.clone())),
+            None => Err(Error::InvalidIntrospection(format!(
+                "{} type without a name",
+                type_ref.kind
+            ))),
+        },
+    }
+}
+
+fn type_name(type_ref: &TypeRef) -> Result<String> {
+    type_ref.name.clone().ok_or_else(|| {
+        Error::InvalidIntrospection(format!("{} type without a name", type_ref.kind))
+    })
+}
+
+fn deprecated(is_deprecated: bool, reason: &Option<String>) -> Vec<Directive<'static, String>> {
+    if !is_deprecated {
+        return Vec::new();
+    }
+
+    let arguments = match reason {
+        Some(reason) if reason != DEFAULT_DEPRECATION_REASON => {
+            vec![("reason".to_string(), Value::String(reason.clone()))]
+        }
+        _ => Vec::new(),
+    };
+
+    vec![Directive {
+        position: Pos::default(),
+        name: "deprecated".to_string(),
+        arguments,
+    }]
+}
+
+fn to_input_value(value: &IntrospectionInputValue) -> Result<InputValue<'static, String>> {
+    Ok(InputValue {
+        position: Pos::default(),
+        description: value.description.clone(),
+        name: value.name.clone(),
+        value_type: to_type(&value.type_ref)?,
+        // default values are already GraphQL literals, enum values are
+        // written as they are so we don't need to parse them
+        default_value: value.default_value.clone().map(Value::Enum),
+        directives: deprecated(value.is_deprecated, &value.deprecation_reason),
+    })
+}
+
+fn to_input_values(values: &[IntrospectionInputValue]) -> Result<Vec<InputValue<'static, String>>> {
+    values.iter().map(to_input_value).collect()
+}
+
+fn to_fields(full_type: &FullType) -> Result<Vec<Field<'static, String>>> {
+    full_type
+        .fields
+        .iter()
+        .flatten()
+        .map(|field| {
+            Ok(Field {
+                position: Pos::default(),
+                description: field.description.clone(),
+                name: field.name.clone(),
+                arguments: to_input_values(&field.args)?,
+                field_type: to_type(&field.type_ref)?,
+                directives: deprecated(field.is_deprecated, &field.deprecation_reason),
+            })
+        })
+        .collect()
+}
+
+fn to_type_names(type_refs: &Option<Vec<TypeRef>>) -> Result<Vec<String>> {
+    type_refs.iter().flatten().map(type_name).collect()
+}
+
+fn to_type_definition(full_type: &FullType) -> Result<TypeDefinition<'static, String>> {
+    let position = Pos::default();
+    let description = full_type.description.clone();
+    let name = full_type.name.clone();
+
+    let definition = match full_type.kind.as_str() {
+        "OBJECT" => TypeDefinition::Object(ObjectType {
+            position,
+            description,
+            name,
+            implements_interfaces: to_type_names(&full_type.interfaces)?,
+            directives: Vec::new(),
+            fields: to_fields(full_type)?,
+        }),
+        "INTERFACE" => TypeDefinition::Interface(InterfaceType {
+            position,
+            description,
+            name,
+            directives: Vec::new(),
+            fields: to_fields(full_type)?,
+        }),
+        "UNION" => TypeDefinition::Union(UnionType {
+            position,
+            description,
+            name,
+            directives: Vec::new(),
+            types: to_type_names(&full_type.possible_types)?,
+        }),
+        "ENUM" => TypeDefinition::Enum(EnumType {
+            position,
+            description,
+            name,
+            directives: Vec::new(),
+            values: full_type
+                .enum_values
+                .iter()
+                .flatten()
+                .map(|value| EnumValue {
+                    position,
+                    description: value.description.clone(),
+                    name: value.name.clone(),
+                    directives: deprecated(value.is_deprecated, &value.deprecation_reason),
+                })
+                .collect(),
+        }),
+        "INPUT_OBJECT" => TypeDefinition::InputObject(InputObjectType {
+            position,
+            description,
+            name,
+            directives: Vec::new(),
+            fields: to_input_values(full_type.input_fields.as_deref().unwrap_or_default())?,
+        }),
+        "SCALAR" => TypeDefinition::Scalar(ScalarType {
+            position,
+            description,
+            name,
+            directives: match &full_type.specified_by_url {
+                Some(url) => vec![Directive {
+                    position,
+                    name: "specifiedBy".to_string(),
+                    arguments: vec![("url".to_string(), Value::String(url.clone()))],
+                }],
+                None => Vec::new(),
+            },
+        }),
+        kind => {
+            return Err(Error::InvalidIntrospection(format!(
+                "Unknown kind {} for type {}",
+                kind, full_type.name
+            )))
+        }
+    };
+
+    Ok(definition)
+}
+
+fn to_directive_definition(
+    directive: &IntrospectionDirective,
+) -> Result<DirectiveDefinition<'static, String>> {
+    let locations = directive
+        .locations
+        .iter()
+        .map(|location| {
+            location.parse::<DirectiveLocation>().map_err(|_| {
+                Error::InvalidIntrospection(format!(
+                    "Unknown location {} for directive {}",
+                    location, directive.name
+                ))
+            })
+        })
+        .collect::<Result<Vec<DirectiveLocation>>>()?;
+
+    Ok(DirectiveDefinition {
+        position: Pos::default(),
+        description: directive.description.clone(),
+        name: directive.name.clone(),
+        arguments: to_input_values(&directive.args)?,
+        locations,
+    })
+}
+
+/// The `schema { ... }` definition is only needed when the root types
+/// don't follow the conventional names.
+fn to_schema_definition(schema: &IntrospectionSchema) -> Option<SchemaDefinition<'static, String>> {
+    let roots = [
+        (&schema.query_type, "Query"),
+        (&schema.mutation_type, "Mutation"),
+        (&schema.subscription_type, "Subscription"),
+    ];
+
+    let is_conventional = roots.iter().all(|(root, conventional)| match root {
+        Some(root) => root.name == *conventional,
+        None => !schema.types.iter().any(|t| t.name == *conventional),
+    });
+
+    if is_conventional {
+        return None;
+    }
+
+    let name = |root: &Option<NamedTypeRef>| root.as_ref().map(|root| root.name.clone());
+
+    Some(SchemaDefinition {
+        position: Pos::default(),
+        directives: Vec::new(),
+        query: name(&schema.query_type),
+        mutation: name(&schema.mutation_type),
+        subscription: name(&schema.subscription_type),
+    })
+}
+
+/// Converts an introspection result to an SDL document, keeping
+/// descriptions, deprecations, directives and default values. Built-in
+/// scalars, directives and introspection types are left out.
+pub fn introspection_to_document(json: &str) -> Result<Document<'static, String>> {
+    let json: Json =
+        serde_json::from_str(json).map_err(|e| Error::InvalidIntrospection(e.to_string()))?;
+    let schema = find_schema(json)?;
+    let mut definitions: Vec<Definition<'static, String>> = Vec::new();
+
+    if let Some(schema_definition) = to_schema_definition(&schema) {
+        definitions.push(Definition::SchemaDefinition(schema_definition));
+    }
+
+    for directive in &schema.directives {
+        if !BUILT_IN_DIRECTIVES
+            .iter()
+            .any(|(name, ..)| *name == directive.name)
+        {
+            definitions.push(Definition::DirectiveDefinition(to_directive_definition(
+                directive,
+            )?));
+        }
+    }
+
+    for full_type in &schema.types {
+        if full_type.name.starts_with("__") || BUILT_IN_SCALARS.contains(&full_type.name.as_str()) {
+            continue;
+        }
+
+        definitions.push(Definition::TypeDefinition(to_type_definition(full_type)?));
+    }
+
+    Ok(Document { definitions })
+}
+
+/// Same as `introspection_to_document`, formatted as SDL.
+pub fn introspection_to_sdl(json: &str) -> Result<String> {
+    introspection_to_document(json).map(|document| document.to_string())
+}
+
+/// Introspection results are JSON objects, while SDL documents never start
+/// with a `{`.
+pub fn is_introspection(content: &str) -> bool {
+    content.trim_start().starts_with('{')
+}
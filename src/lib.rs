@@ -0,0 +1,200 @@
+pub mod document;
+pub mod error;
+pub mod field_renamer;
+pub mod flamegraph;
+mod fragment_registry;
+pub mod introspection;
+mod markdown_extractor;
+pub mod operation_namer;
+mod python_extractor;
+pub mod query_extractor;
+mod rust_extractor;
+pub mod schema_fetcher;
+pub mod schema_loader;
+pub mod schema_stats;
+pub mod traffic_log;
+pub mod usage_finder;
+
+pub use crate::error::{Error, Result};
+pub use crate::introspection::introspection_to_sdl;
+pub use crate::query_extractor::{SkipCategory, SkippedResult};
+pub use crate::schema_fetcher::{fetch_introspection, FetchOptions};
+pub use crate::schema_loader::{load_schema, load_schema_with_options};
+pub use crate::schema_stats::{
+    collect_stats, collect_traffic_stats, schema_stats, Stats, StatsOptions,
+};
+pub use crate::usage_finder::{find_usages, Coordinate, Usage};
+
+use crate::document::DocumentSource;
+use crate::fragment_registry::check_fragment_spreads;
+use crate::markdown_extractor::extract_queries_from_markdown_file;
+use crate::python_extractor::extract_queries_from_python_file;
+use crate::query_extractor::extract_queries_from_file;
+use crate::rust_extractor::extract_queries_from_rust_file;
+use globwalk::GlobWalkerBuilder;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct ExtractionConfig {
+    pub path: PathBuf,
+    /// Glob patterns of paths to leave out
+    pub exclude: Vec<String>,
+    /// Python functions taking a GraphQL document as first argument
+    pub python_functions: Vec<String>,
+    /// Also extract documents from Markdown and MDX code fences
+    pub include_docs: bool,
+    /// Extract Gatsby page and static queries, and check that every fragment
+    /// spread matches a fragment defined somewhere in the project
+    pub gatsby: bool,
+}
+
+impl Default for ExtractionConfig {
+    fn default() -> Self {
+        ExtractionConfig {
+            path: PathBuf::from("."),
+            exclude: Vec::new(),
+            python_functions: vec!["gql".to_string()],
+            include_docs: false,
+            gatsby: false,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ExtractionReport {
+    /// Documents preceded by their `# pal: ...` annotation
+    pub documents: Vec<String>,
+    pub skipped_files: Vec<SkippedResult>,
+}
+
+/// Finds the files with one of the given comma separated `extensions`,
+/// dependencies and build folders are always excluded.
+pub fn find_files(path: &Path, extensions: &str, exclude: &[String]) -> Result<Vec<PathBuf>> {
+    let mut patterns = vec![
+        format!("*.{{{}}}", extensions),
+        "!node_modules".to_string(),
+        "!.next".to_string(),
+        "!.layers".to_string(),
+        "!target".to_string(),
+    ];
+    patterns.extend(exclude.iter().map(|x| format!("!{}", x)));
+
+    let walker = GlobWalkerBuilder::from_patterns(path, &patterns)
+        .follow_links(false)
+        .build()
+        .map_err(|e| Error::InvalidPattern(e.to_string()))?;
+
+    walker
+        .into_iter()
+        .map(|entry| match entry {
+            Ok(entry) => Ok(entry.into_path()),
+            Err(e) => Err(Error::io(
+                e.path().map(Path::to_path_buf).unwrap_or_default(),
+                e.into(),
+            )),
+        })
+        .collect()
+}
+
+fn is_extension(path: &Path, extensions: &[&str]) -> bool {
+    match path.extension().and_then(OsStr::to_str) {
+        Some(extension) => extensions.contains(&extension),
+        None => false,
+    }
+}
+
+/// Extracts the GraphQL documents of every supported file under
+/// `config.path`, files that can't be handled are reported in the skipped
+/// files instead of failing the whole extraction.
+pub fn extract_documents(config: &ExtractionConfig) -> Result<ExtractionReport> {
+    extract_documents_with_progress(config, |_, _| {})
+}
+
+/// Same as `extract_documents`, `on_progress` is called with the number of
+/// processed files and the total after each file.
+pub fn extract_documents_with_progress<F: FnMut(usize, usize)>(
+    config: &ExtractionConfig,
+    mut on_progress: F,
+) -> Result<ExtractionReport> {
+    let extensions = if config.include_docs {
+        "js,ts,tsx,graphql,rs,py,md,mdx"
+    } else {
+        "js,ts,tsx,graphql,rs,py"
+    };
+
+    let mut files = find_files(&config.path, extensions, &config.exclude)?;
+
+    // Rust files reference query files that might be in the tree as
+    // well, we handle them first so we don't extract those twice
+    files.sort_by_key(|path| !is_extension(path, &["rs"]));
+
+    let mut report = ExtractionReport::default();
+    let mut seen_query_paths: HashSet<PathBuf> = HashSet::new();
+
+    for (index, path) in files.iter().enumerate() {
+        extract_documents_from_file(path, config, &mut seen_query_paths, &mut report);
+
+        on_progress(index + 1, files.len());
+    }
+
+    if config.gatsby {
+        report
+            .skipped_files
+            .append(&mut check_fragment_spreads(&report.documents));
+    }
+
+    Ok(report)
+}
+
+fn extract_documents_from_file(
+    path: &Path,
+    config: &ExtractionConfig,
+    seen_query_paths: &mut HashSet<PathBuf>,
+    report: &mut ExtractionReport,
+) {
+    if is_extension(path, &["rs"]) {
+        let mut result = extract_queries_from_rust_file(path, seen_query_paths);
+
+        report.documents.append(&mut result.queries);
+        report.skipped_files.append(&mut result.skipped_files);
+    } else if is_extension(path, &["py"]) {
+        let mut result = extract_queries_from_python_file(path, &config.python_functions);
+
+        report.documents.append(&mut result.queries);
+        report.skipped_files.append(&mut result.skipped_files);
+    } else if is_extension(path, &["md", "mdx"]) {
+        let mut result = extract_queries_from_markdown_file(path);
+
+        report.documents.append(&mut result.queries);
+        report.skipped_files.append(&mut result.skipped_files);
+    } else if is_extension(path, &["graphql"]) {
+        if let Ok(canonical_path) = path.canonicalize() {
+            if seen_query_paths.contains(&canonical_path) {
+                return;
+            }
+        }
+
+        let source = DocumentSource {
+            kind: Some("graphql".to_string()),
+            path: Some(path.display().to_string()),
+            ..Default::default()
+        };
+
+        match read_to_string(path) {
+            Ok(query) => report.documents.push(source.annotate(&query)),
+            Err(e) => {
+                report
+                    .skipped_files
+                    .push(SkippedResult::new(path, SkipCategory::Io, e.to_string()))
+            }
+        }
+    } else {
+        let mut result = extract_queries_from_file(path, config.gatsby);
+
+        report.documents.append(&mut result.queries);
+        report.skipped_files.append(&mut result.skipped_files);
+    }
+}
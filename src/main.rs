@@ -1,24 +1,45 @@
-mod query_extractor;
-mod schema_stats;
-
-use crate::query_extractor::SkippedResult;
 use colored::*;
-use globwalk::DirEntry;
-use globwalk::GlobWalkerBuilder;
-use graphql_parser::parse_query;
+use graphql_pal::field_renamer::{rename_field, FieldRename};
+use graphql_pal::flamegraph::{flamegraph_svg, folded_stacks};
+use graphql_pal::operation_namer::name_operations;
+use graphql_pal::schema_fetcher::parse_header;
+use graphql_pal::schema_stats::{
+    Attribution, StatsArgument, StatsDirective, StatsField, TypeKind, UsageNode,
+};
+use graphql_pal::traffic_log::queries_from_log;
+use graphql_pal::{
+    collect_stats, collect_traffic_stats, extract_documents, extract_documents_with_progress,
+    fetch_introspection, find_files, find_usages, introspection_to_sdl, load_schema_with_options,
+    Coordinate, Error, ExtractionConfig, FetchOptions, SkipCategory, SkippedResult, StatsOptions,
+    Usage,
+};
 use indicatif::ProgressBar;
-use indicatif::ProgressIterator;
 use indicatif::ProgressStyle;
-use query_extractor::extract_queries_from_file;
-use schema_stats::generate_schema_stats;
-use std::convert::TryInto;
-use std::ffi::OsStr;
+use std::collections::BTreeMap;
 use std::fs::read_to_string;
-use std::fs::File;
+use std::fs::{write, File};
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use structopt::StructOpt;
 
+#[derive(StructOpt, Debug)]
+struct FetchArgs {
+    #[structopt(
+        short = "H",
+        long = "header",
+        number_of_values = 1,
+        help = "Header(s) sent to schema URLs, $VAR and ${VAR} are read from the environment"
+    )]
+    headers: Vec<String>,
+    #[structopt(long, help = "Seconds to wait for schema URLs")]
+    timeout: Option<u64>,
+    #[structopt(long, help = "Accept invalid certificates from schema URLs")]
+    insecure: bool,
+    #[structopt(long, help = "PEM certificate to trust for schema URLs")]
+    ca_cert: Option<PathBuf>,
+}
+
 #[derive(StructOpt, Debug)]
 #[structopt(about = "GraphQL pal")]
 enum Command {
@@ -29,12 +50,31 @@ enum Command {
         output: String,
         #[structopt(short = "e", help = "Path(s) to exclude")]
         exclude: Vec<String>,
+        #[structopt(
+            long = "python-function",
+            default_value = "gql",
+            help = "Python function(s) taking a GraphQL document as first argument"
+        )]
+        python_functions: Vec<String>,
+        #[structopt(
+            long,
+            help = "Also extract documents from Markdown and MDX code fences"
+        )]
+        include_docs: bool,
+        #[structopt(
+            long,
+            help = "Extract Gatsby page and static queries, checking fragment spreads project wide"
+        )]
+        gatsby: bool,
     },
     SchemaStats {
         #[structopt()]
         documents: String,
-        #[structopt()]
-        schema: String,
+        #[structopt(
+            required = true,
+            help = "Schema file(s), directories, glob patterns or URLs"
+        )]
+        schema: Vec<String>,
         #[structopt(
             long,
             help = "This will include fields from fragments, even if they are not used"
@@ -42,6 +82,126 @@ enum Command {
         include_fragments: bool,
         #[structopt(long, help = "Output results as JSON")]
         json: bool,
+        #[structopt(
+            long,
+            requires = "json",
+            help = "Output the directive stats as JSON instead of the types"
+        )]
+        directives: bool,
+        #[structopt(
+            long = "exclude-kind",
+            help = "Leave out documents extracted from these sources, for example docs"
+        )]
+        exclude_kinds: Vec<String>,
+        #[structopt(
+            long,
+            help = "Record the operations, fragments and files selecting each field"
+        )]
+        attribution: bool,
+        #[structopt(
+            long,
+            help = "Show the response paths selected from the root types instead of the field counts"
+        )]
+        tree: bool,
+        #[structopt(long, help = "Levels of fields shown in the tree")]
+        depth: Option<usize>,
+        #[structopt(
+            long,
+            default_value = "1",
+            help = "Leave out paths of the tree selected fewer times"
+        )]
+        min_count: i32,
+        #[structopt(long, help = "Output the tree as folded stacks, for flame graph tools")]
+        folded: bool,
+        #[structopt(long, help = "Write the tree as an SVG flame graph to this file")]
+        flamegraph: Option<String>,
+        #[structopt(
+            long,
+            help = "Read the documents from a traffic log, weighting the tree by requests"
+        )]
+        traffic_log: bool,
+        #[structopt(flatten)]
+        fetch: FetchArgs,
+    },
+    NameOperations {
+        #[structopt()]
+        path: String,
+        #[structopt(short = "e", help = "Path(s) to exclude")]
+        exclude: Vec<String>,
+        #[structopt(long, help = "Only print the names, without changing any file")]
+        dry_run: bool,
+    },
+    RenameField {
+        #[structopt(help = "Field to rename, for example User.fullName")]
+        field: String,
+        #[structopt()]
+        new_name: String,
+        #[structopt()]
+        path: String,
+        #[structopt(
+            long,
+            required = true,
+            number_of_values = 1,
+            help = "Schema file(s), directories, glob patterns or URLs"
+        )]
+        schema: Vec<String>,
+        #[structopt(short = "e", help = "Path(s) to exclude")]
+        exclude: Vec<String>,
+        #[structopt(long, help = "Don't add an alias to the old name")]
+        no_alias: bool,
+        #[structopt(long, help = "Only print the usages, without changing any file")]
+        dry_run: bool,
+        #[structopt(flatten)]
+        fetch: FetchArgs,
+    },
+    IntrospectionToSdl {
+        #[structopt(help = "Introspection result, as JSON")]
+        introspection: String,
+        #[structopt(help = "Where to save the SDL, printed when not given")]
+        output: Option<String>,
+    },
+    FindUsages {
+        #[structopt(
+            help = "Schema coordinate, like User, User.email, Query.search(term:) or Role.ADMIN"
+        )]
+        coordinate: String,
+        #[structopt(
+            default_value = ".",
+            help = "Source tree, or documents written by extract-queries"
+        )]
+        path: String,
+        #[structopt(
+            long,
+            required = true,
+            number_of_values = 1,
+            help = "Schema file(s), directories, glob patterns or URLs"
+        )]
+        schema: Vec<String>,
+        #[structopt(short = "e", help = "Path(s) to exclude")]
+        exclude: Vec<String>,
+        #[structopt(
+            long = "python-function",
+            default_value = "gql",
+            help = "Python function(s) taking a GraphQL document as first argument"
+        )]
+        python_functions: Vec<String>,
+        #[structopt(long, help = "Also search Markdown and MDX code fences")]
+        include_docs: bool,
+        #[structopt(flatten)]
+        fetch: FetchArgs,
+    },
+    FetchSchema {
+        #[structopt(help = "GraphQL endpoint to introspect")]
+        url: String,
+        #[structopt(help = "Where to save the schema, printed when not given")]
+        output: Option<String>,
+        #[structopt(
+            long,
+            help = "Save the introspection result instead of SDL, implied by a .json output"
+        )]
+        json: bool,
+        #[structopt(flatten)]
+        fetch: FetchArgs,
     },
 }
 
@@ -55,12 +215,232 @@ fn write_message(message: ColoredString) {
     println!("  {}", message);
 }
 
-fn query_is_valid(query: &str) -> bool {
-    match parse_query::<&str>(query) {
-        Err(_) => {
-            return false;
+fn print_skipped_files(skipped_files: Vec<SkippedResult>, path: &str) {
+    if skipped_files.len() > 0 {
+        let mut categories: BTreeMap<SkipCategory, Vec<SkippedResult>> = BTreeMap::new();
+
+        for file in skipped_files {
+            categories.entry(file.category).or_default().push(file);
+        }
+
+        println!();
+        write_message(String::from("Skipped:").yellow());
+
+        for (category, files) in &categories {
+            write_message(format!("  {:<12} {:>5}", category.name(), files.len()).normal());
         }
-        Ok(_) => true,
+
+        for (category, files) in categories {
+            println!();
+            write_message(format!("{}:", category.name()).yellow());
+
+            for file in files {
+                let file_path = match file.line {
+                    Some(line) => format!("{}:{}", file.path.replace(path, ""), line),
+                    None => file.path.replace(path, ""),
+                };
+
+                write_message(
+                    String::from(format!(
+                        "{}: {}",
+                        file_path,
+                        format!("{}", file.reason).bold()
+                    ))
+                    .white(),
+                );
+            }
+        }
+    }
+}
+
+fn write_usage(name: String, count: i32) {
+    let name = if count == 0 { name.red() } else { name.green() };
+
+    write_message(format!("  {} x {}", name, count).normal());
+}
+
+/// Usages only reached indirectly, through interfaces or variables, are
+/// shown apart from the unused ones.
+fn write_indirect_usage(name: String, count: i32, indirect: &[String]) {
+    if indirect.is_empty() {
+        return write_usage(name, count);
+    }
+
+    let name = if count == 0 {
+        name.yellow()
+    } else {
+        name.green()
+    };
+
+    write_message(format!("  {} x {} ({})", name, count, indirect.join(", ")).normal());
+}
+
+/// Fields show the distinct operations selecting them next to the
+/// selections, which fragments and aliases can repeat.
+fn write_field_usage(name: String, field: &StatsField) {
+    let mut details: Vec<String> = Vec::new();
+
+    if field.operations > 0 {
+        details.push(format!("in {} operations", field.operations));
+    }
+
+    details.extend(
+        [
+            (field.via_interface, "interfaces"),
+            (field.via_implementation, "implementations"),
+            (field.via_variable, "variables"),
+        ]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, source)| format!("+{} via {}", count, source)),
+    );
+
+    if !field.aliases.is_empty() {
+        let mut aliases: Vec<String> = field
+            .aliases
+            .iter()
+            .map(|(alias, count)| format!("{} x {}", alias, count))
+            .collect();
+        aliases.sort();
+
+        details.push(format!("aliased as {}", aliases.join(", ")));
+    }
+
+    write_indirect_usage(name, field.count, &details);
+}
+
+fn write_attribution(coordinate: String, used_by: Attribution) {
+    write_message(format!("  {}", coordinate).green());
+
+    let sources = [
+        ("operations", used_by.operations),
+        ("fragments", used_by.fragments),
+        ("files", used_by.files),
+    ];
+
+    for (label, names) in sources.iter() {
+        if !names.is_empty() {
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+
+            write_message(format!("    {}: {}", label, names.join(", ")).normal());
+        }
+    }
+}
+
+fn write_usage_tree(name: &str, node: &UsageNode, depth: usize) {
+    let name = if depth == 0 {
+        name.magenta().bold()
+    } else {
+        name.green()
+    };
+
+    write_message(
+        format!(
+            "{}{} x {} {}",
+            "  ".repeat(depth),
+            name,
+            node.count,
+            node.r#type.white().italic()
+        )
+        .normal(),
+    );
+
+    for (field, child) in &node.children {
+        write_usage_tree(field, child, depth + 1);
+    }
+}
+
+fn write_directive_usage(directive: &StatsDirective) {
+    println!();
+    write_message(
+        format!(
+            "{} @{} x {}",
+            "directive".white().italic(),
+            directive.name,
+            directive.count
+        )
+        .magenta()
+        .bold(),
+    );
+
+    for (location, count) in &directive.locations {
+        write_usage(location.clone(), *count);
+    }
+
+    for (argument, values) in &directive.arguments {
+        let mut values: Vec<String> = values
+            .iter()
+            .map(|(value, count)| format!("{} x {}", value, count))
+            .collect();
+        values.sort();
+
+        write_message(format!("    {}: [{}]", argument, values.join(", ")).normal());
+    }
+}
+
+fn write_argument_usage(name: &str, argument: &StatsArgument) {
+    let name = format!("{}:", name);
+    let name = if argument.count == 0 {
+        name.red()
+    } else {
+        name.green()
+    };
+
+    let mut values: Vec<String> = argument
+        .values
+        .iter()
+        .map(|(value, count)| format!("{} x {}", value, count))
+        .collect();
+    values.sort();
+
+    let values = if values.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", values.join(", "))
+    };
+
+    write_message(
+        format!(
+            "    {} x {} ({} literal, {} variable){}",
+            name, argument.count, argument.literal, argument.variable, values
+        )
+        .normal(),
+    );
+}
+
+fn exit_with_error(err: Error) -> ! {
+    eprintln!("Error: {}", err);
+    std::process::exit(1);
+}
+
+fn read_file(path: &str) -> String {
+    read_to_string(path).unwrap_or_else(|e| exit_with_error(Error::io(path, e)))
+}
+
+fn write_or_print(output: Option<String>, content: String) {
+    match output {
+        Some(output) => {
+            if let Err(e) = write(&output, content) {
+                exit_with_error(Error::io(output, e));
+            }
+        }
+        None => print!("{}", content),
+    }
+}
+
+fn fetch_options(args: FetchArgs) -> FetchOptions {
+    let headers = args
+        .headers
+        .iter()
+        .map(|header| parse_header(header))
+        .collect::<Result<_, _>>()
+        .unwrap_or_else(|e| exit_with_error(e));
+
+    FetchOptions {
+        headers,
+        timeout: args.timeout.map(Duration::from_secs),
+        insecure: args.insecure,
+        ca_certificate: args.ca_cert,
     }
 }
 
@@ -72,31 +452,24 @@ fn main() {
             path,
             output,
             exclude,
+            python_functions,
+            include_docs,
+            gatsby,
         } => {
             println!("");
             write_message("## Extracting documents".magenta().bold());
             println!("");
             write_message(String::from(format!("Extracting documents from {}\n", path)).normal());
 
-            let mut paths = vec![
-                "*.{js,ts,tsx,graphql}".to_string(),
-                "!node_modules".to_string(),
-                "!.next".to_string(),
-                "!.layers".to_string(),
-            ];
-            paths.extend(exclude.into_iter().map(|x| format!("!{}", x)));
-
-            let walker = GlobWalkerBuilder::from_patterns(path.clone(), &paths)
-                .follow_links(false)
-                .build()
-                .unwrap();
-
-            let files = walker
-                .into_iter()
-                .map(|x| x.unwrap())
-                .collect::<Vec<DirEntry>>();
+            let config = ExtractionConfig {
+                path: PathBuf::from(&path),
+                exclude,
+                python_functions,
+                include_docs,
+                gatsby,
+            };
 
-            let bar = ProgressBar::new(files.len().try_into().unwrap());
+            let bar = ProgressBar::new(0);
 
             bar.set_style(
                 ProgressStyle::default_bar()
@@ -104,55 +477,30 @@ fn main() {
                     .progress_chars("##-"),
             );
 
-            let mut queries: Vec<String> = Vec::new();
-            let mut skipped_files: Vec<SkippedResult> = Vec::new();
+            let report = extract_documents_with_progress(&config, |done, total| {
+                bar.set_length(total as u64);
+                bar.set_position(done as u64);
+            })
+            .unwrap_or_else(|e| exit_with_error(e));
 
-            for entry in files.iter().progress_with(bar) {
-                let path = entry.path();
+            bar.finish();
 
-                if path.extension() == Some(OsStr::new("graphql")) {
-                    let q = read_to_string(path);
-
-                    match q {
-                        Ok(query) => queries.push(query),
-                        Err(e) => skipped_files.push(SkippedResult {
-                            path: path.display().to_string(),
-                            reason: e.to_string(),
-                        }),
-                    }
-                } else {
-                    let q = extract_queries_from_file(path);
-
-                    match q {
-                        Some(mut result) => {
-                            for query in result.queries {
-                                if query_is_valid(&query) {
-                                    queries.push(query.to_string());
-                                } else {
-                                    println!("Invalid query in {}", path.display());
-                                    println!("{}", query);
-                                    panic!();
-                                }
-                            }
-
-                            skipped_files.append(&mut result.skipped_files);
-                        }
-                        None => {}
-                    }
-                }
-            }
+            let queries = report.documents;
+            let skipped_files = report.skipped_files;
 
             let output_path = Path::new(&output);
-            let display = output_path.display();
 
-            let mut file = match File::create(&output_path) {
-                Err(why) => panic!("couldn't create {}: {}", display, why),
-                Ok(file) => file,
-            };
+            let written = File::create(&output_path).and_then(|mut file| {
+                for query in &queries {
+                    file.write_all(query.as_bytes())?;
+                    file.write_all("\n".as_bytes())?;
+                }
+
+                Ok(())
+            });
 
-            for query in &queries {
-                file.write(query.as_bytes()).unwrap();
-                file.write("\n".as_bytes()).unwrap();
+            if let Err(e) = written {
+                exit_with_error(Error::io(output_path, e));
             }
 
             println!("");
@@ -165,61 +513,365 @@ fn main() {
                 .green(),
             );
 
-            if skipped_files.len() > 0 {
-                println!();
-                write_message(
-                    String::from(format!("Skipped {} files:", skipped_files.len())).yellow(),
-                );
-
-                for file in skipped_files {
-                    let file_path = file.path.replace(&path, "");
-
-                    write_message(
-                        String::from(format!(
-                            "{}: {}",
-                            file_path,
-                            format!("{}", file.reason).bold()
-                        ))
-                        .white(),
-                    );
-                }
-            }
+            print_skipped_files(skipped_files, &path);
         }
         Command::SchemaStats {
             documents,
             schema,
             include_fragments,
             json,
+            directives,
+            exclude_kinds: excluded_kinds,
+            attribution,
+            tree,
+            depth,
+            min_count,
+            folded,
+            flamegraph,
+            traffic_log,
+            fetch,
         } => {
-            let queries = read_to_string(documents).expect("Unable to read schema");
+            let documents = read_file(&documents);
+            let schema = load_schema_with_options(&schema, &fetch_options(fetch))
+                .unwrap_or_else(|e| exit_with_error(e));
+
+            let options = StatsOptions {
+                include_fragments,
+                exclude_kinds: excluded_kinds,
+                attribution,
+                tree: tree || folded || flamegraph.is_some(),
+            };
+
+            let stats = if traffic_log {
+                collect_traffic_stats(schema, &queries_from_log(&documents), &options)
+            } else {
+                collect_stats(schema, &documents, &options)
+            }
+            .unwrap_or_else(|e| exit_with_error(e));
+
+            for err in &stats.errors {
+                eprintln!("Error: {}", err);
+            }
 
-            let schema = generate_schema_stats(schema, queries, include_fragments);
+            let roots: BTreeMap<String, UsageNode> = stats
+                .tree
+                .iter()
+                .filter(|(_, root)| root.count >= min_count)
+                .map(|(name, root)| (name.clone(), root.pruned(depth, min_count)))
+                .collect();
 
-            if json {
-                let output =
-                    serde_json::to_string_pretty(&schema).expect("Unable to convert stats to json");
+            if let Some(output) = flamegraph {
+                let svg = flamegraph_svg(&folded_stacks(&roots), "Schema usage")
+                    .unwrap_or_else(|e| exit_with_error(e));
+
+                if let Err(e) = write(&output, svg) {
+                    exit_with_error(Error::io(output, e));
+                }
+            } else if folded {
+                for line in folded_stacks(&roots) {
+                    println!("{}", line);
+                }
+            } else if tree {
+                if json {
+                    let output = serde_json::to_string_pretty(&roots)
+                        .expect("Unable to convert the usage tree to json");
+
+                    println!("{}", output);
+                } else {
+                    for (name, root) in &roots {
+                        println!();
+                        write_usage_tree(name, root, 0);
+                    }
+                }
+            } else if json {
+                let output = if directives {
+                    serde_json::to_string_pretty(&stats.directives)
+                } else {
+                    serde_json::to_string_pretty(&stats.types)
+                }
+                .expect("Unable to convert stats to json");
 
                 println!("{}", output);
             } else {
-                for (name, object_type) in schema {
+                let unused_arguments = stats.unused_arguments();
+                let unused_directives = stats.unused_directives();
+                let undefined_directives = stats.undefined_directives();
+                let mut attributions: Vec<(String, Attribution)> = Vec::new();
+
+                for (name, object_type) in stats.types {
+                    let header = match object_type.kind {
+                        TypeKind::Scalar => format!("{} x {}", name, object_type.count),
+                        _ => name.clone(),
+                    };
+
                     println!("");
                     write_message(
-                        format!("{} {}", "type".white().italic(), name)
+                        format!("{} {}", object_type.kind.keyword().white().italic(), header)
                             .magenta()
                             .bold(),
                     );
 
-                    for (field_name, stats) in object_type.fields {
-                        let name = if stats.count == 0 {
-                            field_name.red()
-                        } else {
-                            field_name.green()
-                        };
+                    for (field_name, field) in object_type.fields {
+                        write_field_usage(field_name.clone(), &field);
+
+                        for (argument_name, argument) in &field.arguments {
+                            write_argument_usage(argument_name, argument);
+                        }
+
+                        if !field.used_by.is_empty() {
+                            attributions.push((format!("{}.{}", name, field_name), field.used_by));
+                        }
+                    }
+
+                    for (member, count) in object_type.members {
+                        write_usage(member, count);
+                    }
+
+                    for (value, count) in object_type.values {
+                        let indirect: Vec<String> = object_type
+                            .values_via_variable
+                            .get(&value)
+                            .map(|count| format!("+{} via variables", count))
+                            .into_iter()
+                            .collect();
+
+                        write_indirect_usage(value, count, &indirect);
+                    }
+                }
+
+                for directive in stats.directives.values() {
+                    if directive.executable {
+                        write_directive_usage(directive);
+                    }
+                }
+
+                if !unused_arguments.is_empty() {
+                    println!();
+                    write_message("Unused arguments:".yellow());
+
+                    for coordinate in unused_arguments {
+                        write_message(format!("  {}", coordinate).red());
+                    }
+                }
+
+                if !attributions.is_empty() {
+                    println!();
+                    write_message("Used by:".yellow());
+
+                    attributions.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+                    for (coordinate, used_by) in attributions {
+                        write_attribution(coordinate, used_by);
+                    }
+                }
+
+                if !unused_directives.is_empty() {
+                    println!();
+                    write_message("Unused directives:".yellow());
 
-                        write_message(format!("  {} x {}", name, stats.count).normal());
+                    for name in unused_directives {
+                        write_message(format!("  @{}", name).red());
                     }
                 }
+
+                if !undefined_directives.is_empty() {
+                    println!();
+                    write_message("Directives missing from the schema:".yellow());
+
+                    for name in undefined_directives {
+                        write_message(format!("  @{}", name).red());
+                    }
+                }
+            }
+        }
+        Command::NameOperations {
+            path,
+            exclude,
+            dry_run,
+        } => {
+            println!("");
+            write_message("## Naming anonymous operations".magenta().bold());
+            println!("");
+
+            let files = find_files(Path::new(&path), "js,ts,tsx,graphql", &exclude)
+                .unwrap_or_else(|e| exit_with_error(e));
+
+            let result = name_operations(&files, dry_run);
+
+            for operation in &result.named {
+                write_message(
+                    format!(
+                        "{}:{} {}",
+                        operation.path.replace(&path, ""),
+                        operation.line,
+                        operation.name.bold()
+                    )
+                    .normal(),
+                );
+            }
+
+            println!("");
+
+            if dry_run {
+                write_message(
+                    format!("Found {} anonymous definitions", result.named.len()).yellow(),
+                );
+            } else {
+                write_message(format!("Named {} definitions", result.named.len()).green());
+            }
+
+            print_skipped_files(result.skipped_files, &path);
+        }
+        Command::RenameField {
+            field,
+            new_name,
+            path,
+            schema,
+            exclude,
+            no_alias,
+            dry_run,
+            fetch,
+        } => {
+            println!("");
+            write_message(
+                format!("## Renaming {} to {}", field, new_name)
+                    .magenta()
+                    .bold(),
+            );
+            println!("");
+
+            let rename = FieldRename::from_coordinate(&field, &new_name, !no_alias)
+                .unwrap_or_else(|e| exit_with_error(e));
+
+            let schema = load_schema_with_options(&schema, &fetch_options(fetch))
+                .unwrap_or_else(|e| exit_with_error(e));
+
+            let files = find_files(Path::new(&path), "js,ts,tsx,graphql", &exclude)
+                .unwrap_or_else(|e| exit_with_error(e));
+
+            let result = rename_field(&files, &rename, &schema, dry_run)
+                .unwrap_or_else(|e| exit_with_error(e));
+
+            for usage in &result.renamed {
+                write_message(format!("{}:{}", usage.path.replace(&path, ""), usage.line).normal());
+            }
+
+            println!("");
+
+            if dry_run {
+                write_message(format!("Found {} selections", result.renamed.len()).yellow());
+            } else {
+                write_message(format!("Renamed {} selections", result.renamed.len()).green());
+            }
+
+            print_skipped_files(result.skipped_files, &path);
+        }
+        Command::IntrospectionToSdl {
+            introspection,
+            output,
+        } => {
+            let json = read_file(&introspection);
+            let sdl = introspection_to_sdl(&json).unwrap_or_else(|e| exit_with_error(e));
+
+            write_or_print(output, sdl);
+        }
+        Command::FindUsages {
+            coordinate,
+            path,
+            schema,
+            exclude,
+            python_functions,
+            include_docs,
+            fetch,
+        } => {
+            println!();
+            write_message(format!("## Usages of {}", coordinate).magenta().bold());
+
+            let parsed_coordinate =
+                Coordinate::parse(&coordinate).unwrap_or_else(|e| exit_with_error(e));
+
+            let schema = load_schema_with_options(&schema, &fetch_options(fetch))
+                .unwrap_or_else(|e| exit_with_error(e));
+
+            let (documents, skipped_files, documents_path) = if Path::new(&path).is_dir() {
+                let config = ExtractionConfig {
+                    path: PathBuf::from(&path),
+                    exclude,
+                    python_functions,
+                    include_docs,
+                    ..Default::default()
+                };
+
+                let report = extract_documents(&config).unwrap_or_else(|e| exit_with_error(e));
+
+                (report.documents.join("\n"), report.skipped_files, None)
+            } else {
+                (read_file(&path), Vec::new(), Some(path.clone()))
+            };
+
+            let usages = find_usages(&schema, &documents, &parsed_coordinate)
+                .unwrap_or_else(|e| exit_with_error(e));
+
+            let mut definitions: BTreeMap<String, Vec<Usage>> = BTreeMap::new();
+
+            for usage in &usages {
+                definitions
+                    .entry(usage.definition.clone())
+                    .or_default()
+                    .push(usage.clone());
+            }
+
+            for (definition, usages) in definitions {
+                println!();
+                write_message(definition.yellow());
+
+                for usage in usages {
+                    // documents without annotations come from the file we read
+                    let file = usage.file.or_else(|| documents_path.clone());
+
+                    let location = match (file, usage.line) {
+                        (Some(file), Some(line)) => format!("{}:{}", file, line),
+                        (Some(file), None) => file,
+                        (None, _) => String::new(),
+                    };
+
+                    let fragment = match usage.fragment {
+                        Some(fragment) if !usage.definition.ends_with(&fragment) => {
+                            format!(" (fragment {})", fragment)
+                        }
+                        _ => String::new(),
+                    };
+
+                    write_message(
+                        format!("  {}{} {}", usage.path.green(), fragment, location.white())
+                            .normal(),
+                    );
+                }
             }
+
+            println!();
+            write_message(format!("Found {} usages", usages.len()).green());
+
+            print_skipped_files(skipped_files, &path);
+        }
+        Command::FetchSchema {
+            url,
+            output,
+            json,
+            fetch,
+        } => {
+            let introspection = fetch_introspection(&url, &fetch_options(fetch))
+                .unwrap_or_else(|e| exit_with_error(e));
+
+            let as_json = json || output.iter().any(|path| path.ends_with(".json"));
+
+            let content = if as_json {
+                introspection
+            } else {
+                introspection_to_sdl(&introspection).unwrap_or_else(|e| exit_with_error(e))
+            };
+
+            write_or_print(output, content);
         }
     }
 }
@@ -0,0 +1,104 @@
+use crate::document::DocumentSource;
+use crate::query_extractor::{ExtractionResult, SkipCategory, SkippedResult};
+use graphql_parser::parse_query;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fs::read_to_string;
+use std::path::Path;
+
+lazy_static! {
+    static ref OPENING_FENCE_RE: Regex =
+        Regex::new(r"^\s*(```+|~~~+)\s*\{?\s*(graphql|gql)\b").unwrap();
+}
+
+/// Extracts the documents in ```graphql and ```gql code fences of Markdown
+/// and MDX files, they are tagged with the `docs` kind so they can be left
+/// out of the stats.
+pub fn extract_queries_from_markdown_file(path: &Path) -> ExtractionResult {
+    let mut queries: Vec<String> = Vec::new();
+    let mut skipped_files: Vec<SkippedResult> = Vec::new();
+
+    let content = match read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            skipped_files.push(SkippedResult::new(path, SkipCategory::Io, e.to_string()));
+
+            return ExtractionResult {
+                queries,
+                skipped_files,
+            };
+        }
+    };
+
+    let mut lines = content.lines().enumerate();
+
+    while let Some((fence_index, line)) = lines.next() {
+        let fence = match OPENING_FENCE_RE.captures(line) {
+            Some(captures) => captures[1].to_string(),
+            None => continue,
+        };
+
+        let mut block: Vec<&str> = Vec::new();
+        let mut start_line = None;
+        let mut closed = false;
+
+        for (index, line) in lines.by_ref() {
+            let trimmed = line.trim();
+
+            if trimmed.starts_with(&fence)
+                && trimmed.chars().all(|c| c == fence.as_bytes()[0] as char)
+            {
+                closed = true;
+
+                break;
+            }
+
+            start_line.get_or_insert(index + 1);
+            block.push(line);
+        }
+
+        // an unclosed fence runs until the end of the file
+        if !closed {
+            skipped_files.push(
+                SkippedResult::new(path, SkipCategory::Parse, "Unclosed code fence".to_string())
+                    .at_line(fence_index + 1),
+            );
+
+            break;
+        }
+
+        let start_line = match start_line {
+            Some(line) => line,
+            None => continue,
+        };
+
+        let query = block.join("\n");
+
+        if let Err(e) = parse_query::<&str>(&query) {
+            skipped_files.push(
+                SkippedResult::new(
+                    path,
+                    SkipCategory::Parse,
+                    format!("Invalid document: {}", e),
+                )
+                .at_line(start_line),
+            );
+
+            continue;
+        }
+
+        let source = DocumentSource {
+            kind: Some("docs".to_string()),
+            path: Some(path.display().to_string()),
+            line: Some(start_line),
+            ..Default::default()
+        };
+
+        queries.push(source.annotate(query.trim_end()));
+    }
+
+    ExtractionResult {
+        queries,
+        skipped_files,
+    }
+}
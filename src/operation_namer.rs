@@ -0,0 +1,513 @@
+use crate::query_extractor::{
+    get_ast_and_source_from_path, is_graphql_tag, line_for_position, SkipCategory, SkippedResult,
+};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs::{read_to_string, write};
+use std::path::{Path, PathBuf};
+use swc_common::SourceFile;
+use swc_ecma_ast::{ClassDecl, Expr, FnDecl, Pat, TaggedTpl, VarDeclarator};
+use swc_ecma_visit::{Node, Visit, VisitWith};
+
+lazy_static! {
+    static ref ANONYMOUS_OPERATION_RE: Regex =
+        Regex::new(r"^\s*(query|mutation|subscription)\s*[({@]").unwrap();
+    static ref SHORTHAND_QUERY_RE: Regex = Regex::new(r"^\s*\{").unwrap();
+    static ref ANONYMOUS_FRAGMENT_RE: Regex = Regex::new(r"^\s*fragment\s+on\b").unwrap();
+    static ref DEFINITION_NAME_RE: Regex =
+        Regex::new(r"\b(query|mutation|subscription|fragment)\s+([_A-Za-z][_0-9A-Za-z]*)").unwrap();
+}
+
+pub struct OperationName {
+    pub path: String,
+    pub line: usize,
+    pub name: String,
+}
+
+pub struct NamingResult {
+    pub named: Vec<OperationName>,
+    pub skipped_files: Vec<SkippedResult>,
+}
+
+#[derive(Clone, Copy)]
+enum DefinitionKind {
+    Query,
+    Mutation,
+    Subscription,
+    Fragment,
+}
+
+impl DefinitionKind {
+    fn suffix(&self) -> &'static str {
+        match self {
+            DefinitionKind::Query => "Query",
+            DefinitionKind::Mutation => "Mutation",
+            DefinitionKind::Subscription => "Subscription",
+            DefinitionKind::Fragment => "Fragment",
+        }
+    }
+}
+
+/// An anonymous definition found at the start of a graphql template.
+struct Candidate {
+    kind: DefinitionKind,
+    // byte offset in the source file where the name has to be inserted
+    offset: usize,
+    // text inserted around the name to keep the definition valid
+    prefix: &'static str,
+    suffix: &'static str,
+    line: usize,
+    component: Option<String>,
+    variable: Option<String>,
+}
+
+struct TemplateCollector<'a> {
+    source: &'a SourceFile,
+    path: &'a Path,
+    components: Vec<String>,
+    variable: Option<String>,
+    candidates: Vec<Candidate>,
+    existing_names: &'a mut HashSet<String>,
+    skipped_files: &'a mut Vec<SkippedResult>,
+}
+
+fn is_component_name(name: &str) -> bool {
+    name.chars()
+        .next()
+        .map_or(false, |c| c.is_ascii_uppercase())
+}
+
+fn is_function(expr: &Expr) -> bool {
+    matches!(expr, Expr::Arrow(_) | Expr::Fn(_))
+}
+
+fn find_anonymous_definition(
+    raw: &str,
+) -> Option<(DefinitionKind, usize, &'static str, &'static str)> {
+    if let Some(m) = ANONYMOUS_FRAGMENT_RE.find(raw) {
+        // insert the name right before `on`
+        return Some((DefinitionKind::Fragment, m.end() - 2, "", " "));
+    }
+
+    if let Some(captures) = ANONYMOUS_OPERATION_RE.captures(raw) {
+        let keyword = captures.get(1).unwrap();
+        let kind = match keyword.as_str() {
+            "mutation" => DefinitionKind::Mutation,
+            "subscription" => DefinitionKind::Subscription,
+            _ => DefinitionKind::Query,
+        };
+
+        return Some((kind, keyword.end(), " ", ""));
+    }
+
+    if let Some(m) = SHORTHAND_QUERY_RE.find(raw) {
+        return Some((DefinitionKind::Query, m.end() - 1, "query ", " "));
+    }
+
+    None
+}
+
+/// Walks the quasis of a template in order, keeping track of nesting so
+/// anonymous definitions are found wherever a definition can start, after
+/// other definitions and after interpolations too.
+struct DefinitionScanner {
+    depth: usize,
+    at_definition_start: bool,
+}
+
+impl Default for DefinitionScanner {
+    fn default() -> Self {
+        DefinitionScanner {
+            depth: 0,
+            at_definition_start: true,
+        }
+    }
+}
+
+impl DefinitionScanner {
+    fn scan(&mut self, raw: &str) -> Vec<(DefinitionKind, usize, &'static str, &'static str)> {
+        let mut definitions = Vec::new();
+        let mut chars = raw.char_indices().peekable();
+
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '#' => {
+                    for (_, c) in chars.by_ref() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                }
+                '"' => {
+                    let block = raw[index..].starts_with("\"\"\"");
+                    let end = if block {
+                        raw[index + 3..]
+                            .find("\"\"\"")
+                            .map(|end| index + 3 + end + 3)
+                    } else {
+                        find_string_end(&raw[index + 1..]).map(|end| index + 1 + end + 1)
+                    };
+
+                    let end = end.unwrap_or(raw.len());
+
+                    while let Some((next, _)) = chars.peek() {
+                        if *next >= end {
+                            break;
+                        }
+
+                        chars.next();
+                    }
+                }
+                c if c.is_whitespace() || c == ',' => {}
+                c => {
+                    if self.at_definition_start && self.depth == 0 {
+                        if let Some((kind, offset, prefix, suffix)) =
+                            find_anonymous_definition(&raw[index..])
+                        {
+                            definitions.push((kind, index + offset, prefix, suffix));
+                        }
+
+                        self.at_definition_start = false;
+                    }
+
+                    match c {
+                        '{' | '(' | '[' => self.depth += 1,
+                        '}' | ')' | ']' => {
+                            self.depth = self.depth.saturating_sub(1);
+
+                            // a definition ends with its selection set
+                            if c == '}' && self.depth == 0 {
+                                self.at_definition_start = true;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        definitions
+    }
+}
+
+/// Returns the offset of the closing quote of a string, `raw` starting right
+/// after the opening one.
+fn find_string_end(raw: &str) -> Option<usize> {
+    let mut escaped = false;
+
+    for (index, c) in raw.char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some(index),
+            _ => escaped = false,
+        }
+    }
+
+    None
+}
+
+impl TemplateCollector<'_> {
+    fn skip(&mut self, category: SkipCategory, reason: String, line: usize) {
+        self.skipped_files
+            .push(SkippedResult::new(self.path, category, reason).at_line(line));
+    }
+}
+
+impl Visit for TemplateCollector<'_> {
+    fn visit_fn_decl(&mut self, n: &FnDecl, _parent: &dyn Node) {
+        let name = n.ident.sym.to_string();
+        let is_component = is_component_name(&name);
+
+        if is_component {
+            self.components.push(name);
+        }
+
+        n.visit_children_with(self);
+
+        if is_component {
+            self.components.pop();
+        }
+    }
+
+    fn visit_class_decl(&mut self, n: &ClassDecl, _parent: &dyn Node) {
+        self.components.push(n.ident.sym.to_string());
+
+        n.visit_children_with(self);
+
+        self.components.pop();
+    }
+
+    fn visit_var_declarator(&mut self, n: &VarDeclarator, _parent: &dyn Node) {
+        let name = match &n.name {
+            Pat::Ident(i) => Some(i.sym.to_string()),
+            _ => None,
+        };
+
+        let is_component = match (&name, &n.init) {
+            (Some(name), Some(init)) => is_component_name(name) && is_function(init),
+            _ => false,
+        };
+
+        if is_component {
+            self.components.push(name.unwrap());
+
+            n.visit_children_with(self);
+
+            self.components.pop();
+        } else {
+            let previous = std::mem::replace(&mut self.variable, name);
+
+            n.visit_children_with(self);
+
+            self.variable = previous;
+        }
+    }
+
+    fn visit_tagged_tpl(&mut self, n: &TaggedTpl, _parent: &dyn Node) {
+        if !is_graphql_tag(n) {
+            return;
+        }
+
+        let full_text = n
+            .quasis
+            .iter()
+            .map(|q| q.raw.value.to_string())
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        for captures in DEFINITION_NAME_RE.captures_iter(&full_text) {
+            let name = &captures[2];
+
+            if name != "on" {
+                self.existing_names.insert(name.to_string());
+            }
+        }
+
+        let mut scanner = DefinitionScanner::default();
+
+        for quasi in n.quasis.iter() {
+            let raw = quasi.raw.value.to_string();
+            let definitions = scanner.scan(&raw);
+
+            if definitions.is_empty() {
+                continue;
+            }
+
+            // quasis start right after the backtick or the interpolation, we
+            // double check that the text matches before trusting the offset
+            let start = (quasi.span.lo().0 - self.source.start_pos.0) as usize;
+
+            if !self.source.src[start..].starts_with(&raw) {
+                self.skip(
+                    SkipCategory::Unsupported,
+                    "Unable to map graphql template back to the source".to_string(),
+                    line_for_position(self.source, quasi.span.lo()),
+                );
+
+                return;
+            }
+
+            for (kind, offset_in_raw, prefix, suffix) in definitions {
+                let offset = start + offset_in_raw;
+                let line = self.source.src[..offset].matches('\n').count() + 1;
+
+                self.candidates.push(Candidate {
+                    kind,
+                    offset,
+                    prefix,
+                    suffix,
+                    line,
+                    component: self.components.last().cloned(),
+                    variable: self.variable.clone(),
+                });
+            }
+        }
+    }
+}
+
+/// Converts identifiers like `USER_QUERY`, `userQuery` or `user-profile` to
+/// `UserQuery`, `UserQuery` and `UserProfile`.
+fn pascal_case(value: &str) -> String {
+    value
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let rest = if part.chars().all(|c| !c.is_ascii_lowercase()) {
+                part[1..].to_lowercase()
+            } else {
+                part[1..].to_string()
+            };
+
+            format!("{}{}", part[..1].to_uppercase(), rest)
+        })
+        .collect()
+}
+
+fn base_name_for_file(path: &Path) -> String {
+    let stem = path.file_stem().and_then(OsStr::to_str).unwrap_or("");
+
+    if stem == "index" {
+        if let Some(parent) = path
+            .parent()
+            .and_then(Path::file_name)
+            .and_then(OsStr::to_str)
+        {
+            return pascal_case(parent);
+        }
+    }
+
+    pascal_case(stem)
+}
+
+fn generate_name(candidate: &Candidate, path: &Path) -> String {
+    let base = match &candidate.component {
+        Some(component) => pascal_case(component),
+        None => base_name_for_file(path),
+    };
+
+    let mut name = base.clone();
+
+    if let Some(variable) = &candidate.variable {
+        let mut variable = pascal_case(variable);
+
+        for suffix in &["Query", "Mutation", "Subscription", "Fragment", "Document"] {
+            if variable.len() > suffix.len() && variable.ends_with(suffix) {
+                variable.truncate(variable.len() - suffix.len());
+            }
+        }
+
+        if variable != base
+            && !["Query", "Mutation", "Subscription", "Fragment"].contains(&&*variable)
+        {
+            name.push_str(&variable);
+        }
+    }
+
+    let suffix = candidate.kind.suffix();
+
+    if !name.ends_with(suffix) {
+        name.push_str(suffix);
+    }
+
+    name
+}
+
+fn unique_name(name: String, used: &mut HashSet<String>) -> String {
+    let mut candidate = name.clone();
+    let mut counter = 2;
+
+    while used.contains(&candidate) {
+        candidate = format!("{}{}", name, counter);
+        counter += 1;
+    }
+
+    used.insert(candidate.clone());
+
+    candidate
+}
+
+/// Finds anonymous operations and fragments in the graphql templates of the
+/// given files and writes a stable name back into the source.
+///
+/// Names are derived from the enclosing component (or the file name) and the
+/// variable holding the template, and are checked against every definition
+/// name found in the given files, `.graphql` files included.
+pub fn name_operations(paths: &[PathBuf], dry_run: bool) -> NamingResult {
+    let mut used_names: HashSet<String> = HashSet::new();
+    let mut skipped_files: Vec<SkippedResult> = Vec::new();
+    let mut candidates: HashMap<PathBuf, (String, Vec<Candidate>)> = HashMap::new();
+
+    for path in paths {
+        if path.extension() == Some(OsStr::new("graphql")) {
+            match read_to_string(path) {
+                Ok(content) => {
+                    for captures in DEFINITION_NAME_RE.captures_iter(&content) {
+                        used_names.insert(captures[2].to_string());
+                    }
+                }
+                Err(e) => {
+                    skipped_files.push(SkippedResult::new(path, SkipCategory::Io, e.to_string()))
+                }
+            }
+
+            continue;
+        }
+
+        let (module, source) = match get_ast_and_source_from_path(path) {
+            Ok(result) => result,
+            Err(skipped) => {
+                skipped_files.push(skipped);
+
+                continue;
+            }
+        };
+
+        let mut collector = TemplateCollector {
+            source: &source,
+            path,
+            components: Vec::new(),
+            variable: None,
+            candidates: Vec::new(),
+            existing_names: &mut used_names,
+            skipped_files: &mut skipped_files,
+        };
+
+        collector.visit_module(&module, &module);
+
+        if !collector.candidates.is_empty() {
+            let file_candidates = collector.candidates;
+
+            candidates.insert(path.clone(), (source.src.to_string(), file_candidates));
+        }
+    }
+
+    let mut named: Vec<OperationName> = Vec::new();
+    let mut files: Vec<&PathBuf> = candidates.keys().collect();
+
+    // sorting keeps the generated names stable between runs
+    files.sort();
+
+    for path in files {
+        let (source, file_candidates) = &candidates[path];
+        let mut edits: Vec<(usize, String)> = Vec::new();
+
+        for candidate in file_candidates {
+            let name = unique_name(generate_name(candidate, path), &mut used_names);
+
+            edits.push((
+                candidate.offset,
+                format!("{}{}{}", candidate.prefix, name, candidate.suffix),
+            ));
+
+            named.push(OperationName {
+                path: path.display().to_string(),
+                line: candidate.line,
+                name,
+            });
+        }
+
+        if dry_run {
+            continue;
+        }
+
+        let mut content = source.clone();
+
+        // apply edits from the end so the offsets stay valid
+        edits.sort_by_key(|edit| std::cmp::Reverse(edit.0));
+
+        for (offset, text) in edits {
+            content.insert_str(offset, &text);
+        }
+
+        if let Err(e) = write(path, content) {
+            skipped_files.push(SkippedResult::new(path, SkipCategory::Io, e.to_string()));
+        }
+    }
+
+    NamingResult {
+        named,
+        skipped_files,
+    }
+}
@@ -0,0 +1,643 @@
+use crate::document::DocumentSource;
+use crate::query_extractor::{EvaluationError, ExtractionResult, SkipCategory, SkippedResult};
+use graphql_parser::parse_query;
+use std::collections::{HashMap, HashSet};
+use std::fs::read_to_string;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Str { prefix: String, value: String },
+    Comment(String),
+    Punct(char),
+    // only emitted for logical lines, newlines inside brackets are ignored
+    Newline,
+}
+
+#[derive(Debug)]
+struct Spanned {
+    token: Token,
+    line: usize,
+    column: usize,
+}
+
+const STRING_PREFIXES: &[&str] = &["r", "u", "f", "b", "rb", "br", "fr", "rf"];
+
+fn unterminated_string(line: usize) -> EvaluationError {
+    (
+        SkipCategory::Parse,
+        format!("Unterminated string at line {}", line),
+    )
+}
+
+/// A small tokenizer for Python, it only knows enough about the language to
+/// find strings, names and comments.
+fn tokenize(source: &str) -> Result<Vec<Spanned>, EvaluationError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens: Vec<Spanned> = Vec::new();
+    let mut i = 0;
+    let mut line = 1;
+    let mut column = 0;
+    let mut depth = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start_line = line;
+        let start_column = column;
+
+        if c == '\n' {
+            if depth == 0 {
+                tokens.push(Spanned {
+                    token: Token::Newline,
+                    line,
+                    column,
+                });
+            }
+
+            i += 1;
+            line += 1;
+            column = 0;
+
+            continue;
+        }
+
+        if c == '\\' && chars.get(i + 1) == Some(&'\n') {
+            i += 2;
+            line += 1;
+            column = 0;
+
+            continue;
+        }
+
+        if c.is_whitespace() {
+            i += 1;
+            column += 1;
+
+            continue;
+        }
+
+        if c == '#' {
+            let end = chars[i..]
+                .iter()
+                .position(|&c| c == '\n')
+                .map_or(chars.len(), |p| i + p);
+
+            tokens.push(Spanned {
+                token: Token::Comment(chars[i + 1..end].iter().collect()),
+                line,
+                column,
+            });
+
+            column += end - i;
+            i = end;
+
+            continue;
+        }
+
+        let mut prefix = String::new();
+
+        if c.is_alphabetic() || c == '_' {
+            let end = chars[i..]
+                .iter()
+                .position(|&c| !(c.is_alphanumeric() || c == '_'))
+                .map_or(chars.len(), |p| i + p);
+            let name: String = chars[i..end].iter().collect();
+
+            column += end - i;
+            i = end;
+
+            let is_prefix = STRING_PREFIXES.contains(&&*name.to_lowercase());
+
+            if !is_prefix || !matches!(chars.get(i), Some('"') | Some('\'')) {
+                tokens.push(Spanned {
+                    token: Token::Name(name),
+                    line: start_line,
+                    column: start_column,
+                });
+
+                continue;
+            }
+
+            prefix = name.to_lowercase();
+        }
+
+        let c = chars[i];
+
+        if c == '"' || c == '\'' {
+            let triple = chars.get(i + 1) == Some(&c) && chars.get(i + 2) == Some(&c);
+            let quote_length = if triple { 3 } else { 1 };
+            let mut j = i + quote_length;
+            let mut value = String::new();
+
+            loop {
+                match chars.get(j) {
+                    None => return Err(unterminated_string(start_line)),
+                    Some('\\') => {
+                        if let Some(&next) = chars.get(j + 1) {
+                            if next == '\n' {
+                                line += 1;
+                            }
+
+                            value.push('\\');
+                            value.push(next);
+                        }
+
+                        j += 2;
+                    }
+                    Some(&q)
+                        if q == c
+                            && (!triple
+                                || (chars.get(j + 1) == Some(&c)
+                                    && chars.get(j + 2) == Some(&c))) =>
+                    {
+                        j += quote_length;
+
+                        break;
+                    }
+                    Some('\n') if !triple => return Err(unterminated_string(start_line)),
+                    Some(&other) => {
+                        if other == '\n' {
+                            line += 1;
+                        }
+
+                        value.push(other);
+                        j += 1;
+                    }
+                }
+            }
+
+            let value = if prefix.contains('r') {
+                value
+            } else {
+                unescape(&value)
+            };
+
+            tokens.push(Spanned {
+                token: Token::Str { prefix, value },
+                line: start_line,
+                column: start_column,
+            });
+
+            column += j - i;
+            i = j;
+
+            continue;
+        }
+
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = if depth > 0 { depth - 1 } else { 0 },
+            _ => {}
+        }
+
+        tokens.push(Spanned {
+            token: Token::Punct(c),
+            line,
+            column,
+        });
+
+        i += 1;
+        column += 1;
+    }
+
+    Ok(tokens)
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('\n') => {}
+            Some(other @ '\\') | Some(other @ '\'') | Some(other @ '"') => result.push(other),
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
+            }
+            None => result.push('\\'),
+        }
+    }
+
+    result
+}
+
+struct PythonModule {
+    tokens: Vec<Spanned>,
+    // module level assignments, pointing to the first token of the value
+    constants: HashMap<String, usize>,
+}
+
+impl PythonModule {
+    fn new(tokens: Vec<Spanned>) -> Self {
+        let mut constants = HashMap::new();
+        let mut depth = 0;
+
+        for (index, spanned) in tokens.iter().enumerate() {
+            // names starting a line inside brackets are keyword arguments
+            // or dict keys, not assignments
+            match spanned.token {
+                Token::Punct('(') | Token::Punct('[') | Token::Punct('{') => depth += 1,
+                Token::Punct(')') | Token::Punct(']') | Token::Punct('}') => {
+                    depth = if depth > 0 { depth - 1 } else { 0 }
+                }
+                _ => {}
+            }
+
+            if depth > 0 {
+                continue;
+            }
+
+            if let (Token::Name(name), 0) = (&spanned.token, spanned.column) {
+                let is_assignment = matches!(
+                    tokens.get(index + 1).map(|t| &t.token),
+                    Some(Token::Punct('='))
+                ) && !matches!(
+                    tokens.get(index + 2).map(|t| &t.token),
+                    Some(Token::Punct('='))
+                );
+
+                if is_assignment {
+                    constants.insert(name.clone(), index + 2);
+                }
+            }
+        }
+
+        PythonModule { tokens, constants }
+    }
+
+    fn token(&self, index: usize) -> Option<&Token> {
+        self.tokens.get(index).map(|t| &t.token)
+    }
+
+    /// Line of a token, the last line of the file past the end.
+    fn line(&self, index: usize) -> usize {
+        self.tokens
+            .get(index)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |t| t.line)
+    }
+
+    /// Statically evaluates a string expression made of literals, f-strings,
+    /// `+` concatenations, `%` formatting and module level constants.
+    /// Returns the value and the index of the first token after it.
+    fn evaluate(
+        &self,
+        index: usize,
+        resolving: &mut HashSet<String>,
+    ) -> Result<(String, usize), EvaluationError> {
+        let (mut value, mut index) = self.evaluate_term(index, resolving)?;
+
+        while let Some(Token::Punct('+')) = self.token(index) {
+            let (right, next) = self.evaluate_term(index + 1, resolving)?;
+
+            value.push_str(&right);
+            index = next;
+        }
+
+        Ok((value, index))
+    }
+
+    fn evaluate_term(
+        &self,
+        index: usize,
+        resolving: &mut HashSet<String>,
+    ) -> Result<(String, usize), EvaluationError> {
+        let (value, index) = self.evaluate_atom(index, resolving)?;
+
+        if let Some(Token::Punct('%')) = self.token(index) {
+            let mut arguments: Vec<String> = Vec::new();
+            let mut next = index + 1;
+
+            if let Some(Token::Punct('(')) = self.token(next) {
+                next += 1;
+
+                while self.token(next) != Some(&Token::Punct(')')) {
+                    let (argument, after) = self.evaluate(next, resolving)?;
+
+                    arguments.push(argument);
+                    next = after;
+
+                    if let Some(Token::Punct(',')) = self.token(next) {
+                        next += 1;
+                    }
+                }
+
+                next += 1;
+            } else {
+                let (argument, after) = self.evaluate_atom(next, resolving)?;
+
+                arguments.push(argument);
+                next = after;
+            }
+
+            return Ok((percent_format(&value, &arguments)?, next));
+        }
+
+        Ok((value, index))
+    }
+
+    fn evaluate_atom(
+        &self,
+        index: usize,
+        resolving: &mut HashSet<String>,
+    ) -> Result<(String, usize), EvaluationError> {
+        match self.token(index) {
+            Some(Token::Str { .. }) => {
+                let mut value = String::new();
+                let mut index = index;
+
+                // adjacent literals are concatenated by Python
+                while let Some(Token::Str {
+                    prefix,
+                    value: part,
+                }) = self.token(index)
+                {
+                    if prefix.contains('f') {
+                        value.push_str(&self.format_f_string(part, resolving)?);
+                    } else {
+                        value.push_str(part);
+                    }
+
+                    index += 1;
+                }
+
+                Ok((value, index))
+            }
+            Some(Token::Name(name)) => Ok((self.resolve(name, resolving)?, index + 1)),
+            Some(Token::Punct('(')) => {
+                let (value, next) = self.evaluate(index + 1, resolving)?;
+
+                match self.token(next) {
+                    Some(Token::Punct(')')) => Ok((value, next + 1)),
+                    _ => Err(self.unsupported(next)),
+                }
+            }
+            _ => Err(self.unsupported(index)),
+        }
+    }
+
+    fn resolve(
+        &self,
+        name: &str,
+        resolving: &mut HashSet<String>,
+    ) -> Result<String, EvaluationError> {
+        let index = match self.constants.get(name) {
+            Some(index) => *index,
+            None => {
+                return Err((
+                    SkipCategory::Resolve,
+                    format!("Unable to find value for {}", name),
+                ))
+            }
+        };
+
+        if !resolving.insert(name.to_string()) {
+            return Err((
+                SkipCategory::Resolve,
+                format!("Circular reference to {}", name),
+            ));
+        }
+
+        let (value, next) = self.evaluate(index, resolving)?;
+
+        resolving.remove(name);
+
+        match self.token(next) {
+            None | Some(Token::Newline) | Some(Token::Comment(_)) => Ok(value),
+            _ => Err(self.unsupported(next)),
+        }
+    }
+
+    fn format_f_string(
+        &self,
+        value: &str,
+        resolving: &mut HashSet<String>,
+    ) -> Result<String, EvaluationError> {
+        let mut result = String::new();
+        let mut chars = value.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    result.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    result.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => {
+                                return Err((
+                                    SkipCategory::Parse,
+                                    "Unterminated f-string field".to_string(),
+                                ))
+                            }
+                        }
+                    }
+
+                    let name = name.trim();
+
+                    if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                        return Err((
+                            SkipCategory::Unsupported,
+                            format!("Unsupported f-string field {{{}}}", name),
+                        ));
+                    }
+
+                    result.push_str(&self.resolve(name, resolving)?);
+                }
+                _ => result.push(c),
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn unsupported(&self, index: usize) -> EvaluationError {
+        match self.tokens.get(index) {
+            Some(spanned) => (
+                SkipCategory::Unsupported,
+                format!(
+                    "Unsupported expression {:?} at line {}",
+                    spanned.token, spanned.line
+                ),
+            ),
+            None => (SkipCategory::Parse, "Unexpected end of file".to_string()),
+        }
+    }
+}
+
+fn percent_format(template: &str, arguments: &[String]) -> Result<String, EvaluationError> {
+    let mut result = String::new();
+    let mut arguments = arguments.iter();
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+
+            continue;
+        }
+
+        match chars.next() {
+            Some('%') => result.push('%'),
+            Some('s') => match arguments.next() {
+                Some(argument) => result.push_str(argument),
+                None => {
+                    return Err((
+                        SkipCategory::Parse,
+                        "Not enough arguments for % formatting".to_string(),
+                    ))
+                }
+            },
+            other => {
+                return Err((
+                    SkipCategory::Unsupported,
+                    format!("Unsupported % format {:?}", other),
+                ))
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// Finds GraphQL documents in Python code: the first argument of calls to
+/// one of `functions` (like `gql("...")`) and constants preceded by a
+/// `# graphql` comment.
+pub fn extract_queries_from_python_file(path: &Path, functions: &[String]) -> ExtractionResult {
+    let mut queries: Vec<String> = Vec::new();
+    let mut skipped_files: Vec<SkippedResult> = Vec::new();
+
+    let tokens = match read_to_string(path)
+        .map_err(|e| (SkipCategory::Io, e.to_string()))
+        .and_then(|source| tokenize(&source))
+    {
+        Ok(tokens) => tokens,
+        Err((category, reason)) => {
+            skipped_files.push(SkippedResult::new(path, category, reason));
+
+            return ExtractionResult {
+                queries,
+                skipped_files,
+            };
+        }
+    };
+
+    let module = PythonModule::new(tokens);
+    // line of the expression, for skips, line where the value starts, for
+    // the document annotation, and the value
+    let mut found: Vec<(usize, usize, Result<String, EvaluationError>)> = Vec::new();
+
+    for (index, spanned) in module.tokens.iter().enumerate() {
+        match &spanned.token {
+            Token::Name(name) if functions.contains(name) => {
+                let is_definition =
+                    index > 0 && module.token(index - 1) == Some(&Token::Name("def".to_string()));
+
+                if module.token(index + 1) == Some(&Token::Punct('(')) && !is_definition {
+                    let value = module.evaluate(index + 2, &mut HashSet::new()).and_then(
+                        |(value, next)| match module.token(next) {
+                            Some(Token::Punct(')')) | Some(Token::Punct(',')) => Ok(value),
+                            _ => Err(module.unsupported(next)),
+                        },
+                    );
+
+                    found.push((spanned.line, module.line(index + 2), value));
+                }
+            }
+            Token::Comment(comment) if comment.trim().eq_ignore_ascii_case("graphql") => {
+                let name = match (module.token(index + 1), module.token(index + 2)) {
+                    (Some(Token::Newline), Some(Token::Name(name))) => name,
+                    _ => {
+                        found.push((
+                            spanned.line,
+                            spanned.line,
+                            Err((
+                                SkipCategory::Parse,
+                                "Expected an assignment after # graphql".to_string(),
+                            )),
+                        ));
+
+                        continue;
+                    }
+                };
+
+                let (value, value_line) = match module.constants.get(name) {
+                    Some(value_index) => (
+                        module.resolve(name, &mut HashSet::new()),
+                        module.line(*value_index),
+                    ),
+                    None => (
+                        Err((
+                            SkipCategory::Parse,
+                            format!("Expected an assignment to {}", name),
+                        )),
+                        spanned.line + 1,
+                    ),
+                };
+
+                found.push((spanned.line + 1, value_line, value));
+            }
+            _ => {}
+        }
+    }
+
+    let mut seen: HashSet<String> = HashSet::new();
+
+    for (line, value_line, value) in found {
+        match value {
+            Ok(query) => {
+                // the document starts after the blank lines we trim
+                let leading = &query[..query.len() - query.trim_start().len()];
+                let document_line = value_line + leading.matches('\n').count();
+                let query = query.trim().to_string();
+
+                if let Err(e) = parse_query::<&str>(&query) {
+                    skipped_files.push(
+                        SkippedResult::new(
+                            path,
+                            SkipCategory::Parse,
+                            format!("Invalid GraphQL document: {}", e),
+                        )
+                        .at_line(line),
+                    );
+                } else if seen.insert(query.clone()) {
+                    let source = DocumentSource {
+                        kind: Some("python".to_string()),
+                        path: Some(path.display().to_string()),
+                        line: Some(document_line),
+                        ..Default::default()
+                    };
+
+                    queries.push(source.annotate(&query));
+                }
+            }
+            Err((category, reason)) => {
+                skipped_files.push(SkippedResult::new(path, category, reason).at_line(line))
+            }
+        }
+    }
+
+    ExtractionResult {
+        queries,
+        skipped_files,
+    }
+}
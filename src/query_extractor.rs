@@ -2,18 +2,20 @@ extern crate globwalk;
 extern crate swc_common;
 extern crate swc_ecma_parser;
 
+use crate::document::DocumentSource;
+use graphql_parser::parse_query;
 use md5;
-use std::path::Path;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
 use swc_atoms::JsWord;
 use swc_common::sync::Lrc;
-use swc_common::{
-    errors::{ColorConfig, Handler},
-    SourceMap,
-};
+use swc_common::{SourceFile, SourceMap};
 use swc_ecma_ast::Decl::Var;
 use swc_ecma_ast::Expr::TaggedTpl;
 use swc_ecma_ast::ExprOrSuper::Expr;
 use swc_ecma_ast::ImportSpecifier::Named;
+use swc_ecma_ast::ImportSpecifier::Namespace;
 use swc_ecma_ast::MemberExpr;
 use swc_ecma_ast::Module;
 use swc_ecma_ast::ModuleDecl::ExportDecl;
@@ -25,20 +27,74 @@ use swc_ecma_ast::Expr::{Ident, Member};
 use swc_ecma_parser::{lexer::Lexer, EsConfig, Parser, StringInput, Syntax, TsConfig};
 use swc_ecma_visit::{Node, Visit};
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SkipCategory {
+    /// The file couldn't be read or written
+    Io,
+    /// The file or the document in it isn't valid
+    Parse,
+    /// A value used in the document couldn't be found
+    Resolve,
+    /// The file uses something we don't know how to evaluate
+    Unsupported,
+}
+
+impl SkipCategory {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SkipCategory::Io => "io",
+            SkipCategory::Parse => "parse",
+            SkipCategory::Resolve => "resolve",
+            SkipCategory::Unsupported => "unsupported",
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct SkippedResult {
     pub path: String,
     pub reason: String,
+    pub category: SkipCategory,
+    /// Line of the file the reason refers to, when known
+    pub line: Option<usize>,
+}
+
+impl SkippedResult {
+    pub fn new<P: AsRef<Path>>(path: P, category: SkipCategory, reason: String) -> Self {
+        SkippedResult {
+            path: path.as_ref().display().to_string(),
+            reason,
+            category,
+            line: None,
+        }
+    }
+
+    pub fn at_line(mut self, line: usize) -> Self {
+        self.line = Some(line);
+        self
+    }
 }
 
+/// Why an interpolated expression couldn't be evaluated.
+pub type EvaluationError = (SkipCategory, String);
+
 pub struct QueryExtractor<'a> {
     pub queries: &'a mut Vec<String>,
     pub skipped_files: &'a mut Vec<SkippedResult>,
     module: &'a Module,
+    source: &'a SourceFile,
     path: &'a Path,
+    gatsby: bool,
 }
 
-fn is_graphql_tag(node: &swc_ecma_ast::TaggedTpl) -> bool {
+/// Returns the 1-based line of a position in the file.
+pub fn line_for_position(source: &SourceFile, pos: swc_common::BytePos) -> usize {
+    let offset = (pos.0 - source.start_pos.0) as usize;
+
+    source.src[..offset].matches('\n').count() + 1
+}
+
+pub fn is_graphql_tag(node: &swc_ecma_ast::TaggedTpl) -> bool {
     match &*node.tag {
         Member(m) => match &m.obj {
             Expr(e) => match &**e {
@@ -61,22 +117,170 @@ fn is_graphql_tag(node: &swc_ecma_ast::TaggedTpl) -> bool {
     }
 }
 
-fn find_import_for_name(name: String, module: &Module) -> Option<String> {
+/// Gatsby uses a global `graphql` tag, both for page queries and for
+/// `useStaticQuery`.
+fn is_gatsby_graphql_tag(node: &swc_ecma_ast::TaggedTpl) -> bool {
+    match &*node.tag {
+        Ident(t) => t.sym == JsWord::from("graphql"),
+        _ => false,
+    }
+}
+
+/// A name imported from another module, `name` is `None` when the whole
+/// module is imported, like with `import * as x` or `const x = require(...)`.
+/// `path` is the module source as written, see `resolve_module_path`.
+struct ImportedName {
+    path: String,
+    name: Option<String>,
+}
+
+/// Extensions tried, in order, for imports written without one. These are
+/// the extensions `get_ast_and_source_from_path` can parse.
+const MODULE_EXTENSIONS: [&str; 6] = ["js", "jsx", "ts", "tsx", "mjs", "cjs"];
+
+/// Resolves an import source relative to the directory of the importing
+/// module the way bundlers do: the file itself, then the file with one of
+/// `MODULE_EXTENSIONS`, then an `index` file when the source is a directory.
+fn resolve_module_path(directory: &Path, source: &str) -> Option<PathBuf> {
+    let path = directory.join(source);
+
+    if path.is_file() {
+        return Some(path);
+    }
+
+    let with_extension = MODULE_EXTENSIONS.iter().map(|extension| {
+        let mut file = path.clone().into_os_string();
+
+        file.push(".");
+        file.push(extension);
+
+        PathBuf::from(file)
+    });
+    let index = MODULE_EXTENSIONS
+        .iter()
+        .map(|extension| path.join(format!("index.{}", extension)));
+
+    with_extension
+        .chain(index)
+        .find(|candidate| candidate.is_file())
+}
+
+/// Returns the module path when the expression is a `require('...')` call.
+fn find_require_source(expr: &swc_ecma_ast::Expr) -> Option<String> {
+    match expr {
+        swc_ecma_ast::Expr::Call(call) => {
+            let is_require = match &call.callee {
+                Expr(callee) => match &**callee {
+                    Ident(i) => i.sym == JsWord::from("require"),
+                    _ => false,
+                },
+                _ => false,
+            };
+
+            match (is_require, call.args.get(0).map(|arg| &*arg.expr)) {
+                (true, Some(swc_ecma_ast::Expr::Lit(swc_ecma_ast::Lit::Str(s)))) => {
+                    Some(s.value.to_string())
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Finds the module and the properties read from it in members like
+/// `require('./fragments').UserFragment`.
+fn find_required_member(expr: &MemberExpr) -> Option<(String, Vec<String>)> {
+    if expr.computed {
+        return None;
+    }
+
+    let property = match &*expr.prop {
+        Ident(i) => i.sym.to_string(),
+        _ => return None,
+    };
+    let object = match &expr.obj {
+        Expr(e) => &**e,
+        _ => return None,
+    };
+
+    let (source, mut properties) = match object {
+        Member(m) => find_required_member(m)?,
+        _ => (find_require_source(object)?, Vec::new()),
+    };
+
+    properties.push(property);
+
+    Some((source, properties))
+}
+
+fn prop_name_to_string(key: &swc_ecma_ast::PropName) -> Option<String> {
+    match key {
+        swc_ecma_ast::PropName::Ident(i) => Some(i.sym.to_string()),
+        swc_ecma_ast::PropName::Str(s) => Some(s.value.to_string()),
+        _ => None,
+    }
+}
+
+/// Finds the name bound by `const { name } = require(...)`,
+/// `const { exported: name } = require(...)` or `const name = require(...)`.
+fn find_required_name(name: &str, decl: &swc_ecma_ast::VarDeclarator) -> Option<ImportedName> {
+    let source = find_require_source(decl.init.as_ref()?)?;
+
+    match &decl.name {
+        swc_ecma_ast::Pat::Ident(i) if i.sym == *name => Some(ImportedName {
+            path: source.clone(),
+            name: None,
+        }),
+        swc_ecma_ast::Pat::Object(o) => o.props.iter().find_map(|prop| match prop {
+            swc_ecma_ast::ObjectPatProp::Assign(a) if a.key.sym == *name => Some(ImportedName {
+                path: source.clone(),
+                name: Some(name.to_string()),
+            }),
+            swc_ecma_ast::ObjectPatProp::KeyValue(kv) => match &*kv.value {
+                swc_ecma_ast::Pat::Ident(i) if i.sym == *name => Some(ImportedName {
+                    path: source.clone(),
+                    name: Some(prop_name_to_string(&kv.key)?),
+                }),
+                _ => None,
+            },
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+fn find_import_for_name(name: String, module: &Module) -> Option<ImportedName> {
     for item in module.body.iter() {
         match item {
             ModuleDecl(d) => match d {
                 Import(i) => {
-                    if i.specifiers.iter().any(|s| match s {
-                        Named(n) => n.local.sym == name,
-                        _ => false,
-                    }) {
-                        let mut path = i.src.value.to_string();
-                        path.push_str(".js");
-                        return Some(path);
+                    for specifier in i.specifiers.iter() {
+                        let imported_name = match specifier {
+                            Named(n) if n.local.sym == name => Some(
+                                n.imported
+                                    .as_ref()
+                                    .map_or(name.clone(), |imported| imported.sym.to_string()),
+                            ),
+                            Namespace(n) if n.local.sym == name => None,
+                            _ => continue,
+                        };
+
+                        return Some(ImportedName {
+                            path: i.src.value.to_string(),
+                            name: imported_name,
+                        });
                     }
                 }
                 _ => {}
             },
+            swc_ecma_ast::ModuleItem::Stmt(swc_ecma_ast::Stmt::Decl(Var(v))) => {
+                for decl in v.decls.iter() {
+                    if let Some(imported) = find_required_name(&name, decl) {
+                        return Some(imported);
+                    }
+                }
+            }
             _ => {}
         }
     }
@@ -84,84 +288,487 @@ fn find_import_for_name(name: String, module: &Module) -> Option<String> {
     None
 }
 
-fn find_value_from_file(name: String, path: &Path) -> Option<String> {
-    let module = get_ast_from_path(path).unwrap();
+/// Finds the value of a top level variable, exported or not.
+fn find_local_declaration<'a>(name: &str, module: &'a Module) -> Option<&'a swc_ecma_ast::Expr> {
+    for item in module.body.iter() {
+        let var = match item {
+            ModuleDecl(ExportDecl(e)) => match &e.decl {
+                Var(v) => v,
+                _ => continue,
+            },
+            swc_ecma_ast::ModuleItem::Stmt(swc_ecma_ast::Stmt::Decl(Var(v))) => v,
+            _ => continue,
+        };
+
+        for decl in var.decls.iter() {
+            let init = decl.init.as_ref().map(|init| &**init);
+
+            // required modules are handled by `find_import_for_name`
+            if init.and_then(find_require_source).is_some() {
+                continue;
+            }
+
+            match &decl.name {
+                swc_ecma_ast::Pat::Ident(i) if i.sym == *name => return init,
+                _ => {}
+            }
+        }
+    }
+
+    None
+}
+
+/// Finds a property in an object literal, shorthand properties are
+/// resolved to the variable with the same name.
+fn find_object_property<'a>(
+    name: &str,
+    expr: &'a swc_ecma_ast::Expr,
+    module: &'a Module,
+) -> Option<&'a swc_ecma_ast::Expr> {
+    let object = match expr {
+        swc_ecma_ast::Expr::Object(o) => o,
+        _ => return None,
+    };
+
+    for prop in object.props.iter() {
+        match prop {
+            swc_ecma_ast::PropOrSpread::Prop(p) => match &**p {
+                swc_ecma_ast::Prop::KeyValue(kv) => {
+                    if prop_name_to_string(&kv.key).as_deref() == Some(name) {
+                        return Some(&*kv.value);
+                    }
+                }
+                swc_ecma_ast::Prop::Shorthand(i) if i.sym == *name => {
+                    return find_local_declaration(name, module);
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Returns the names on the left side of an assignment, for example
+/// `["module", "exports", "UserFragment"]`.
+fn find_assignment_target(left: &swc_ecma_ast::PatOrExpr) -> Vec<String> {
+    let mut parts = match left {
+        swc_ecma_ast::PatOrExpr::Expr(e) => find_name_for_expr(e),
+        swc_ecma_ast::PatOrExpr::Pat(p) => match &**p {
+            swc_ecma_ast::Pat::Expr(e) => find_name_for_expr(e),
+            swc_ecma_ast::Pat::Ident(i) => vec![i.sym.to_string()],
+            _ => vec![],
+        },
+    };
+
+    parts.reverse();
+
+    parts
+}
+
+/// Finds an exported value, supporting both ES modules (`export const`) and
+/// CommonJS (`exports.X = `, `module.exports.X = ` and `module.exports = {}`).
+fn find_export<'a>(name: &str, module: &'a Module) -> Option<&'a swc_ecma_ast::Expr> {
+    let mut value: Option<&swc_ecma_ast::Expr> = None;
 
     for item in module.body.iter() {
         match item {
-            ModuleDecl(d) => match d {
-                ExportDecl(e) => match &e.decl {
-                    Var(v) => {
-                        // assuming we only declare one variable at the time
-                        let decl = v.decls.get(0).unwrap();
-
-                        if match &decl.name {
-                            swc_ecma_ast::Pat::Ident(i) => i.sym == name,
-                            _ => false,
-                        } {
-                            let value = decl.init.as_ref().unwrap();
+            ModuleDecl(ExportDecl(e)) => match &e.decl {
+                Var(v) => {
+                    for decl in v.decls.iter() {
+                        match &decl.name {
+                            swc_ecma_ast::Pat::Ident(i) if i.sym == *name => {
+                                value = decl.init.as_ref().map(|init| &**init);
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            },
+            swc_ecma_ast::ModuleItem::Stmt(swc_ecma_ast::Stmt::Expr(s)) => match &*s.expr {
+                swc_ecma_ast::Expr::Assign(a) => {
+                    let target = find_assignment_target(&a.left);
+                    let target = target.iter().map(|p| p.as_str()).collect::<Vec<&str>>();
 
-                            match &**value {
-                                TaggedTpl(tpl) => return Some(tpl.quasis[0].raw.value.to_string()),
-                                _ => {}
+                    match target.as_slice() {
+                        ["exports", n] | ["module", "exports", n] if *n == name => {
+                            value = Some(&*a.right);
+                        }
+                        ["module", "exports"] => {
+                            if let Some(v) = find_object_property(name, &a.right, module) {
+                                value = Some(v);
                             }
                         }
+                        _ => {}
                     }
-                    _ => {}
-                },
+                }
                 _ => {}
             },
             _ => {}
         }
     }
 
-    None
+    match value {
+        // this happens with `exports.X = X`
+        Some(Ident(i)) => find_local_declaration(&i.sym, module),
+        _ => value,
+    }
+}
+
+// imports and helpers can reference each other, this protects us from cycles
+const MAX_EVALUATION_DEPTH: usize = 32;
+
+/// Values bound to function parameters while evaluating a helper call.
+type Scope = HashMap<String, String>;
+
+/// A function we know how to evaluate: its parameter names and the
+/// expression it returns.
+struct LocalFunction<'a> {
+    params: Vec<String>,
+    body: &'a swc_ecma_ast::Expr,
+}
+
+fn param_names<'a, I: Iterator<Item = &'a swc_ecma_ast::Pat>>(params: I) -> Option<Vec<String>> {
+    params
+        .map(|pat| match pat {
+            swc_ecma_ast::Pat::Ident(i) => Some(i.sym.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn find_returned_expr(body: &swc_ecma_ast::BlockStmt) -> Option<&swc_ecma_ast::Expr> {
+    match body.stmts.as_slice() {
+        [swc_ecma_ast::Stmt::Return(r)] => r.arg.as_ref().map(|arg| &**arg),
+        _ => None,
+    }
+}
+
+/// Finds a top level function that only returns an expression, either as
+/// a function declaration or as an arrow function.
+fn find_local_function<'a>(name: &str, module: &'a Module) -> Option<LocalFunction<'a>> {
+    for item in module.body.iter() {
+        let decl = match item {
+            ModuleDecl(ExportDecl(e)) => &e.decl,
+            swc_ecma_ast::ModuleItem::Stmt(swc_ecma_ast::Stmt::Decl(d)) => d,
+            _ => continue,
+        };
+
+        match decl {
+            swc_ecma_ast::Decl::Fn(f) if f.ident.sym == *name => {
+                return Some(LocalFunction {
+                    params: param_names(f.function.params.iter().map(|p| &p.pat))?,
+                    body: find_returned_expr(f.function.body.as_ref()?)?,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    match find_local_declaration(name, module)? {
+        swc_ecma_ast::Expr::Arrow(arrow) => Some(LocalFunction {
+            params: param_names(arrow.params.iter())?,
+            body: match &arrow.body {
+                swc_ecma_ast::BlockStmtOrExpr::Expr(e) => &**e,
+                swc_ecma_ast::BlockStmtOrExpr::BlockStmt(b) => find_returned_expr(b)?,
+            },
+        }),
+        _ => None,
+    }
+}
+
+fn evaluate_template(
+    quasis: &[swc_ecma_ast::TplElement],
+    exprs: &[Box<swc_ecma_ast::Expr>],
+    module: &Module,
+    module_path: &Path,
+    scope: &Scope,
+    depth: usize,
+) -> Result<String, EvaluationError> {
+    let mut parts = Vec::<String>::new();
+
+    // template literals are divided in quasis and expressions, see:
+    // https://astexplorer.net/#/gist/56fa8c1b00bbf670fd06df091165cf07
+    // we want to merge the quasis and replace the expressions with the
+    // actual query content, usually fragments
+
+    for (pos, quasi) in quasis.iter().enumerate() {
+        parts.push(quasi.raw.value.to_string());
+
+        if let Some(expr) = exprs.get(pos) {
+            parts.push(evaluate_expr(expr, module, module_path, scope, depth)?);
+        }
+    }
+
+    Ok(parts.join(""))
+}
+
+/// Statically evaluates an expression interpolated in a template, following
+/// imports, local constants and simple helpers returning an expression.
+fn evaluate_expr(
+    expr: &swc_ecma_ast::Expr,
+    module: &Module,
+    module_path: &Path,
+    scope: &Scope,
+    depth: usize,
+) -> Result<String, EvaluationError> {
+    if depth > MAX_EVALUATION_DEPTH {
+        return Err((
+            SkipCategory::Resolve,
+            "Too many nested references when evaluating expression".to_string(),
+        ));
+    }
+
+    let depth = depth + 1;
+
+    match expr {
+        swc_ecma_ast::Expr::Lit(swc_ecma_ast::Lit::Str(s)) => Ok(s.value.to_string()),
+        swc_ecma_ast::Expr::Tpl(tpl) => {
+            evaluate_template(&tpl.quasis, &tpl.exprs, module, module_path, scope, depth)
+        }
+        TaggedTpl(tpl) if is_graphql_tag(tpl) => {
+            evaluate_template(&tpl.quasis, &tpl.exprs, module, module_path, scope, depth)
+        }
+        swc_ecma_ast::Expr::Paren(p) => evaluate_expr(&p.expr, module, module_path, scope, depth),
+        swc_ecma_ast::Expr::Bin(b) if b.op == swc_ecma_ast::BinaryOp::Add => Ok(format!(
+            "{}{}",
+            evaluate_expr(&b.left, module, module_path, scope, depth)?,
+            evaluate_expr(&b.right, module, module_path, scope, depth)?
+        )),
+        Ident(i) => {
+            let name = i.sym.to_string();
+
+            if let Some(value) = scope.get(&name) {
+                return Ok(value.clone());
+            }
+
+            if let Some(value) = find_local_declaration(&name, module) {
+                return evaluate_expr(value, module, module_path, &Scope::new(), depth);
+            }
+
+            match find_import_for_name(name.clone(), module) {
+                Some(imported) => evaluate_import(imported, vec![], &name, module_path, depth),
+                None => Err((
+                    SkipCategory::Resolve,
+                    format!("Unable to find import for {}", name),
+                )),
+            }
+        }
+        Member(m) => evaluate_member(m, module, module_path, depth),
+        swc_ecma_ast::Expr::Call(call) => evaluate_call(call, module, module_path, scope, depth),
+        _ => Err((
+            SkipCategory::Unsupported,
+            format!("Unsupported {}", expression_kind(expr)),
+        )),
+    }
 }
 
-fn find_value_for_expr(
-    name: String,
+/// Describes an expression for skip reasons, the template line is reported
+/// along with it.
+fn expression_kind(expr: &swc_ecma_ast::Expr) -> &'static str {
+    match expr {
+        swc_ecma_ast::Expr::This(_) => "this expression",
+        swc_ecma_ast::Expr::Array(_) => "array literal",
+        swc_ecma_ast::Expr::Object(_) => "object literal",
+        swc_ecma_ast::Expr::Fn(_) => "function expression",
+        swc_ecma_ast::Expr::Arrow(_) => "arrow function",
+        swc_ecma_ast::Expr::Class(_) => "class expression",
+        swc_ecma_ast::Expr::Unary(_) => "unary expression",
+        swc_ecma_ast::Expr::Update(_) => "update expression",
+        swc_ecma_ast::Expr::Bin(_) => "binary expression",
+        swc_ecma_ast::Expr::Assign(_) => "assignment",
+        swc_ecma_ast::Expr::Cond(_) => "conditional expression",
+        swc_ecma_ast::Expr::Seq(_) => "sequence expression",
+        swc_ecma_ast::Expr::New(_) => "new expression",
+        swc_ecma_ast::Expr::Await(_) => "await expression",
+        swc_ecma_ast::Expr::Tpl(_) => "template literal",
+        swc_ecma_ast::Expr::TaggedTpl(_) => "tagged template",
+        swc_ecma_ast::Expr::Call(_) => "call",
+        swc_ecma_ast::Expr::Lit(_) => "literal",
+        Ident(_) => "identifier",
+        Member(_) => "member expression",
+        _ => "expression",
+    }
+}
+
+/// Evaluates `[A, B].join('')` and calls to local helpers.
+fn evaluate_call(
+    call: &swc_ecma_ast::CallExpr,
     module: &Module,
     module_path: &Path,
-) -> Result<String, String> {
-    match find_import_for_name(name.clone(), module) {
-        Some(path) => {
-            let imported_module_path = Path::new(&path);
-            let abs_path = module_path
-                .parent()
-                .unwrap()
-                .join(imported_module_path)
-                .canonicalize();
-
-            match abs_path {
-                Ok(path) => {
-                    let value = find_value_from_file(name.clone(), &path);
-
-                    match value {
-                        Some(t) => return Ok(t),
-                        None => Err(format!("Unable to find value from import for {}", name)),
+    scope: &Scope,
+    depth: usize,
+) -> Result<String, EvaluationError> {
+    let callee = match &call.callee {
+        Expr(callee) => &**callee,
+        _ => {
+            return Err((
+                SkipCategory::Unsupported,
+                "Unsupported call to super".to_string(),
+            ))
+        }
+    };
+
+    let mut args = Vec::<String>::new();
+
+    for arg in call.args.iter() {
+        if arg.spread.is_some() {
+            return Err((
+                SkipCategory::Unsupported,
+                "Unsupported spread argument".to_string(),
+            ));
+        }
+
+        args.push(evaluate_expr(&arg.expr, module, module_path, scope, depth)?);
+    }
+
+    match callee {
+        Member(m) => {
+            let array = match (&m.obj, &*m.prop, m.computed) {
+                (Expr(obj), Ident(prop), false) if prop.sym == JsWord::from("join") => match &**obj
+                {
+                    swc_ecma_ast::Expr::Array(array) => array,
+                    _ => {
+                        return Err((
+                            SkipCategory::Unsupported,
+                            "Unsupported call, only arrays can be joined".to_string(),
+                        ))
                     }
+                },
+                _ => {
+                    let mut parts = find_name_for_member(m);
+                    parts.reverse();
+
+                    let name = match parts.len() {
+                        0 => expression_kind(callee).to_string(),
+                        _ => parts.join("."),
+                    };
+
+                    return Err((
+                        SkipCategory::Unsupported,
+                        format!("Unsupported call to {}", name),
+                    ));
+                }
+            };
+
+            let mut elements = Vec::<String>::new();
+
+            for element in array.elems.iter() {
+                match element {
+                    Some(e) if e.spread.is_none() => {
+                        elements.push(evaluate_expr(&e.expr, module, module_path, scope, depth)?)
+                    }
+                    _ => {
+                        return Err((
+                            SkipCategory::Unsupported,
+                            "Unsupported array element".to_string(),
+                        ))
+                    }
+                }
+            }
+
+            let separator = args.get(0).map_or(",", |s| s.as_str());
+
+            Ok(elements.join(separator))
+        }
+        Ident(i) => {
+            let function = match find_local_function(&i.sym, module) {
+                Some(function) => function,
+                None => {
+                    return Err((
+                        SkipCategory::Resolve,
+                        format!("Unable to evaluate call to {}", i.sym),
+                    ))
                 }
-                Err(e) => Err(format!("Got error when trying to find {}: {}", name, e)),
+            };
+
+            let mut function_scope = Scope::new();
+
+            for (pos, param) in function.params.into_iter().enumerate() {
+                let value = args.get(pos).cloned().unwrap_or_default();
+
+                function_scope.insert(param, value);
             }
+
+            evaluate_expr(function.body, module, module_path, &function_scope, depth)
         }
-        None => Err(format!("Unable to find import for {}", name)),
+        _ => Err((
+            SkipCategory::Unsupported,
+            format!("Unsupported call to {}", expression_kind(callee)),
+        )),
     }
 }
 
-fn _find_name_for_expr(expr: &swc_ecma_ast::Expr) -> Vec<String> {
+fn evaluate_import(
+    imported: ImportedName,
+    mut properties: Vec<String>,
+    display_name: &str,
+    module_path: &Path,
+    depth: usize,
+) -> Result<String, EvaluationError> {
+    let directory = module_path.parent().unwrap_or_else(|| Path::new(""));
+    let abs_path = resolve_module_path(directory, &imported.path)
+        .ok_or_else(|| {
+            (
+                SkipCategory::Resolve,
+                format!(
+                    "Unable to find module {} for {}",
+                    imported.path, display_name
+                ),
+            )
+        })?
+        .canonicalize()
+        .map_err(|e| {
+            (
+                SkipCategory::Resolve,
+                format!("Got error when trying to find {}: {}", display_name, e),
+            )
+        })?;
+
+    if let Some(name) = imported.name {
+        properties.insert(0, name);
+    }
+
+    let not_found = || {
+        (
+            SkipCategory::Resolve,
+            format!("Unable to find value from import for {}", display_name),
+        )
+    };
+
+    let imported_module = get_ast_from_path(&abs_path)
+        .map_err(|e| (e.category, format!("{} (in {})", e.reason, e.path)))?;
+    let (name, properties) = properties.split_first().ok_or_else(not_found)?;
+    let mut value = find_export(name, &imported_module).ok_or_else(not_found)?;
+
+    for property in properties {
+        value = find_object_property(property, value, &imported_module).ok_or_else(not_found)?;
+    }
+
+    evaluate_expr(value, &imported_module, &abs_path, &Scope::new(), depth)
+}
+
+fn find_name_for_expr(expr: &swc_ecma_ast::Expr) -> Vec<String> {
     match expr {
         Ident(i) => vec![i.sym.to_string()],
-        Member(m) => _find_name_for_member(m),
+        Member(m) => find_name_for_member(m),
         _ => vec![],
     }
 }
 
-fn _find_name_for_member(expr: &MemberExpr) -> Vec<String> {
-    let mut parts = _find_name_for_expr(&*expr.prop);
+fn find_name_for_member(expr: &MemberExpr) -> Vec<String> {
+    // we don't evaluate things like `fragments[name]`
+    if expr.computed {
+        return vec![];
+    }
+
+    let mut parts = find_name_for_expr(&*expr.prop);
 
     match &expr.obj {
         Expr(e) => {
-            parts.append(&mut _find_name_for_expr(&e));
+            parts.append(&mut find_name_for_expr(&e));
         }
         _ => {}
     };
@@ -169,78 +776,111 @@ fn _find_name_for_member(expr: &MemberExpr) -> Vec<String> {
     return parts;
 }
 
-fn find_value_for_member(
-    _expr: &MemberExpr,
-    _module: &Module,
-    _module_path: &Path,
-) -> Result<String, String> {
-    // let mut parts = find_name_for_member(expr);
-    // parts.reverse();
+fn evaluate_member(
+    expr: &MemberExpr,
+    module: &Module,
+    module_path: &Path,
+    depth: usize,
+) -> Result<String, EvaluationError> {
+    if let Some((source, properties)) = find_required_member(expr) {
+        let display_name = format!("require('{}').{}", source, properties.join("."));
+        let imported = ImportedName {
+            path: source,
+            name: None,
+        };
+
+        return evaluate_import(imported, properties, &display_name, module_path, depth);
+    }
 
-    // TODO: we don't support finding values for member expressions at
-    // the moment so we just return an empty string for now 😊
+    let mut parts = find_name_for_member(expr);
+    parts.reverse();
 
-    return Ok("".to_string());
+    // computed members like `fragments[name]` have no name to show
+    let display_name = match parts.len() {
+        0 => "computed member expression".to_string(),
+        _ => parts.join("."),
+    };
+    let unsupported = || {
+        (
+            SkipCategory::Unsupported,
+            format!("Unable to evaluate {}", display_name),
+        )
+    };
+
+    let (object, properties) = match parts.split_first() {
+        Some((object, properties)) if !properties.is_empty() => (object, properties),
+        _ => return Err(unsupported()),
+    };
+
+    if let Some(mut value) = find_local_declaration(object, module) {
+        for property in properties {
+            value = find_object_property(property, value, module).ok_or_else(|| {
+                (
+                    SkipCategory::Resolve,
+                    format!("Unable to find value for {}", display_name),
+                )
+            })?;
+        }
+
+        return evaluate_expr(value, module, module_path, &Scope::new(), depth);
+    }
+
+    match find_import_for_name(object.clone(), module) {
+        Some(imported) => evaluate_import(
+            imported,
+            properties.to_vec(),
+            &display_name,
+            module_path,
+            depth,
+        ),
+        // like static properties on components
+        None => Err(unsupported()),
+    }
 }
 
 impl Visit for QueryExtractor<'_> {
     fn visit_tagged_tpl(&mut self, n: &swc_ecma_ast::TaggedTpl, _parent: &dyn Node) {
-        if is_graphql_tag(n) {
-            let mut parts = Vec::<String>::new();
+        if self.gatsby && is_gatsby_graphql_tag(n) {
+            // fragments are global in Gatsby, so there is nothing to inline
+            if !n.exprs.is_empty() {
+                self.skipped_files.push(
+                    SkippedResult::new(
+                        self.path,
+                        SkipCategory::Unsupported,
+                        "Gatsby queries can't contain interpolations".to_string(),
+                    )
+                    .at_line(line_for_position(self.source, n.span.lo())),
+                );
 
-            // template literals are divided in quasis and expressions, see:
-            // https://astexplorer.net/#/gist/56fa8c1b00bbf670fd06df091165cf07
-            // we want to merge the quasis and replace the expressions with the
-            // actual query content, usually fragments
-
-            for (pos, quasi) in n.quasis.iter().enumerate() {
-                parts.push(quasi.raw.value.to_string());
-
-                if n.exprs.len() > pos {
-                    let expr = &n.exprs[pos];
+                return;
+            }
 
-                    match &**expr {
-                        Ident(i) => {
-                            let name = i.sym.to_string();
+            let value = &n.quasis[0].raw.value;
 
-                            let value = find_value_for_expr(name, self.module, self.path);
+            self.push_query(value.trim().to_string(), leading_lines(value), n);
+        } else if is_graphql_tag(n) {
+            let value = evaluate_template(
+                &n.quasis,
+                &n.exprs,
+                self.module,
+                self.path,
+                &Scope::new(),
+                0,
+            );
 
-                            match value {
-                                Ok(v) => parts.push(v),
-                                Err(e) => {
-                                    self.skipped_files.push(SkippedResult {
-                                        path: self.path.display().to_string(),
-                                        reason: e,
-                                    });
+            let value = match value {
+                Ok(v) => v,
+                Err((category, reason)) => {
+                    self.skipped_files.push(
+                        SkippedResult::new(self.path, category, reason)
+                            .at_line(line_for_position(self.source, n.span.lo())),
+                    );
 
-                                    return;
-                                }
-                            }
-                        }
-                        Member(m) => match find_value_for_member(m, self.module, self.path) {
-                            Ok(v) => parts.push(v),
-                            Err(e) => {
-                                self.skipped_files.push(SkippedResult {
-                                    path: self.path.display().to_string(),
-                                    reason: e,
-                                });
-
-                                return;
-                            }
-                        },
-                        _ => {
-                            self.skipped_files.push(SkippedResult {
-                                path: self.path.display().to_string(),
-                                reason: format!("Unsupported expression {:?}", expr),
-                            });
-
-                            return;
-                        }
-                    }
+                    return;
                 }
-            }
+            };
 
-            let mut query = parts.join("").trim().to_string();
+            let mut query = value.trim().to_string();
 
             // this adds names to anonymous fragments
             if query.starts_with("fragment on ") {
@@ -249,18 +889,151 @@ impl Visit for QueryExtractor<'_> {
                 query = query.replace("fragment on ", &format!("fragment F_{:x} on ", digest));
             }
 
-            self.queries.push(query);
+            self.push_query(query, leading_lines(&value), n);
+        }
+    }
+}
+
+/// Counts the lines trimmed from the start of a template, so documents
+/// report the line they start on rather than the line of the backtick.
+fn leading_lines(value: &str) -> usize {
+    value[..value.len() - value.trim_start().len()]
+        .matches('\n')
+        .count()
+}
+
+impl QueryExtractor<'_> {
+    /// Annotates and keeps the evaluated template when it's a valid document.
+    fn push_query(&mut self, query: String, leading_lines: usize, node: &swc_ecma_ast::TaggedTpl) {
+        let line = line_for_position(self.source, node.span.lo()) + leading_lines;
+
+        if let Err(e) = parse_query::<&str>(&query) {
+            self.skipped_files.push(
+                SkippedResult::new(
+                    self.path,
+                    SkipCategory::Parse,
+                    format!("Invalid GraphQL document: {}", e),
+                )
+                .at_line(line),
+            );
+
+            return;
+        }
+
+        let source = DocumentSource {
+            kind: Some("javascript".to_string()),
+            path: Some(self.path.display().to_string()),
+            line: Some(line),
+            ..Default::default()
+        };
+
+        self.queries.push(source.annotate(&query));
+    }
+}
+
+pub struct GraphQLTemplate {
+    /// Byte offset of the template content in the source file
+    pub offset: usize,
+    /// Template content, interpolations are replaced with whitespace so
+    /// positions in the text match positions in the source file
+    pub text: String,
+}
+
+struct TemplateFinder<'a> {
+    source: &'a SourceFile,
+    path: &'a Path,
+    templates: Vec<GraphQLTemplate>,
+    errors: Vec<SkippedResult>,
+}
+
+impl Visit for TemplateFinder<'_> {
+    fn visit_tagged_tpl(&mut self, n: &swc_ecma_ast::TaggedTpl, _parent: &dyn Node) {
+        if !is_graphql_tag(n) {
+            return;
+        }
+
+        let src = &self.source.src;
+        let mut offset: Option<usize> = None;
+        let mut text = String::new();
+
+        for quasi in n.quasis.iter() {
+            let raw = quasi.raw.value.to_string();
+            let lo = (quasi.span.lo().0 - self.source.start_pos.0) as usize;
+
+            if !src[lo..].starts_with(&raw) {
+                self.errors.push(
+                    SkippedResult::new(
+                        self.path,
+                        SkipCategory::Unsupported,
+                        "Unable to map graphql template back to the source".to_string(),
+                    )
+                    .at_line(line_for_position(self.source, n.span.lo())),
+                );
+
+                return;
+            }
+
+            match offset {
+                None => offset = Some(lo),
+                Some(start) => {
+                    // blank out the interpolation, but keep newlines so
+                    // line numbers still match the source
+                    for c in src[start + text.len()..lo].chars() {
+                        match c {
+                            '\n' => text.push('\n'),
+                            _ => text.push_str(&" ".repeat(c.len_utf8())),
+                        }
+                    }
+                }
+            }
+
+            text.push_str(&raw);
+        }
+
+        if let Some(offset) = offset {
+            self.templates.push(GraphQLTemplate { offset, text });
         }
     }
 }
 
-fn get_ast_from_path(path: &Path) -> Result<Module, ()> {
+/// Finds all the graphql templates in a file, returning the file content
+/// along with them so callers can rewrite the templates in place.
+pub fn find_graphql_templates(
+    path: &Path,
+) -> Result<(String, Vec<GraphQLTemplate>), SkippedResult> {
+    let (module, source) = get_ast_and_source_from_path(path)?;
+
+    let mut finder = TemplateFinder {
+        source: &source,
+        path,
+        templates: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    finder.visit_module(&module, &module);
+
+    match finder.errors.pop() {
+        Some(e) => Err(e),
+        None => Ok((source.src.to_string(), finder.templates)),
+    }
+}
+
+fn get_ast_from_path(path: &Path) -> Result<Module, SkippedResult> {
+    get_ast_and_source_from_path(path).map(|(module, _)| module)
+}
+
+/// Same as `get_ast_from_path` but also returns the loaded source file, which
+/// is needed when we want to map spans back to the original text.
+pub fn get_ast_and_source_from_path(
+    path: &Path,
+) -> Result<(Module, Lrc<SourceFile>), SkippedResult> {
     let cm: Lrc<SourceMap> = Default::default();
-    let handler = Handler::with_tty_emitter(ColorConfig::Auto, true, false, Some(cm.clone()));
-    let fm = cm.load_file(path).expect("Failed to load file.");
+    let fm = cm
+        .load_file(path)
+        .map_err(|e| SkippedResult::new(path, SkipCategory::Io, e.to_string()))?;
 
-    let syntax = if path.extension().unwrap() == "js" {
-        Syntax::Es(EsConfig {
+    let syntax = match path.extension().and_then(OsStr::to_str) {
+        Some("js") | Some("jsx") | Some("mjs") | Some("cjs") => Syntax::Es(EsConfig {
             jsx: true,
             num_sep: true,
             class_private_props: true,
@@ -277,30 +1050,35 @@ fn get_ast_from_path(path: &Path) -> Result<Module, ()> {
             import_meta: true,
             top_level_await: true,
             import_assertions: true,
-        })
-    } else {
-        Syntax::Typescript(TsConfig {
+        }),
+        Some("ts") | Some("tsx") => Syntax::Typescript(TsConfig {
             tsx: true,
             decorators: true,
             dynamic_import: true,
             dts: false,
             no_early_errors: true,
             import_assertions: false,
-        })
+        }),
+        _ => {
+            return Err(SkippedResult::new(
+                path,
+                SkipCategory::Unsupported,
+                "Unsupported file extension".to_string(),
+            ))
+        }
     };
 
     let lexer = Lexer::new(syntax, JscTarget::Es2020, StringInput::from(&*fm), None);
 
     let mut parser = Parser::new_from(lexer);
 
-    for e in parser.take_errors() {
-        e.into_diagnostic(&handler).emit();
-    }
+    // recoverable errors are ignored, we only need the module to be usable
+    let module = parser.parse_module().map_err(|e| {
+        SkippedResult::new(path, SkipCategory::Parse, e.kind().msg().to_string())
+            .at_line(line_for_position(&fm, e.span().lo()))
+    })?;
 
-    parser.parse_module().map_err(|e| {
-        // Unrecoverable fatal error occurred
-        e.into_diagnostic(&handler).emit()
-    })
+    Ok((module, fm))
 }
 
 pub struct ExtractionResult {
@@ -308,31 +1086,29 @@ pub struct ExtractionResult {
     pub skipped_files: Vec<SkippedResult>,
 }
 
-pub fn extract_queries_from_file(path: &Path) -> Option<ExtractionResult> {
+pub fn extract_queries_from_file(path: &Path, gatsby: bool) -> ExtractionResult {
     let mut queries: Vec<String> = Vec::new();
     let mut skipped_files: Vec<SkippedResult> = Vec::new();
-    let result = get_ast_from_path(path);
+    let result = get_ast_and_source_from_path(path);
 
     match result {
-        Ok(module) => {
+        Ok((module, source)) => {
             let mut extractor = QueryExtractor {
                 path: path,
                 queries: &mut queries,
                 module: &module,
+                source: &source,
                 skipped_files: &mut skipped_files,
+                gatsby,
             };
 
             extractor.visit_module(&module, &module);
-
-            return Some(ExtractionResult {
-                queries: queries.clone(),
-                skipped_files: skipped_files.clone(),
-            });
-        }
-        Err(_) => {
-            println!("failed to parse module {}", path.display());
         }
+        Err(skipped) => skipped_files.push(skipped),
     }
 
-    None
+    ExtractionResult {
+        queries,
+        skipped_files,
+    }
 }
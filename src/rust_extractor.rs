@@ -0,0 +1,143 @@
+use crate::document::DocumentSource;
+use crate::query_extractor::{ExtractionResult, SkipCategory, SkippedResult};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashSet;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+lazy_static! {
+    static ref GRAPHQL_ATTRIBUTE_RE: Regex =
+        Regex::new(r"#\[\s*graphql\s*\(([^\]]*)\)\s*\]").unwrap();
+    static ref QUERY_PATH_RE: Regex = Regex::new(r#"\bquery_path\s*=\s*"([^"]+)""#).unwrap();
+    static ref NAME_RE: Regex = Regex::new(r#"^name\s*=\s*"([^"]+)""#).unwrap();
+}
+
+/// The crate a Rust source file belongs to, `graphql_client` resolves the
+/// paths in its attributes relative to the crate root.
+struct Crate {
+    root: PathBuf,
+    name: String,
+}
+
+fn find_package_name(manifest: &str) -> Option<String> {
+    let mut in_package = false;
+
+    for line in manifest.lines() {
+        let line = line.trim();
+
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if in_package {
+            if let Some(captures) = NAME_RE.captures(line) {
+                return Some(captures[1].to_string());
+            }
+        }
+    }
+
+    None
+}
+
+fn find_crate(path: &Path) -> Option<Crate> {
+    for directory in path.ancestors().skip(1) {
+        let manifest = directory.join("Cargo.toml");
+
+        if let Ok(content) = read_to_string(&manifest) {
+            if let Some(name) = find_package_name(&content) {
+                return Some(Crate {
+                    root: directory.to_path_buf(),
+                    name,
+                });
+            }
+        }
+    }
+
+    None
+}
+
+/// Extracts the documents referenced by `#[graphql(query_path = "...")]`
+/// attributes, as used by the `graphql_client` derive.
+///
+/// `seen_query_paths` is shared between files, as usually many structs
+/// point to the same query file, one per operation.
+pub fn extract_queries_from_rust_file(
+    path: &Path,
+    seen_query_paths: &mut HashSet<PathBuf>,
+) -> ExtractionResult {
+    let mut queries: Vec<String> = Vec::new();
+    let mut skipped_files: Vec<SkippedResult> = Vec::new();
+
+    let content = match read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            return ExtractionResult {
+                queries,
+                skipped_files: vec![SkippedResult::new(path, SkipCategory::Io, e.to_string())],
+            }
+        }
+    };
+
+    let query_paths = GRAPHQL_ATTRIBUTE_RE
+        .captures_iter(&content)
+        .filter_map(|attribute| {
+            QUERY_PATH_RE
+                .captures(&attribute[1])
+                .map(|captures| captures[1].to_string())
+        })
+        .collect::<Vec<String>>();
+
+    if query_paths.is_empty() {
+        return ExtractionResult {
+            queries,
+            skipped_files,
+        };
+    }
+
+    let owner = match find_crate(path) {
+        Some(owner) => owner,
+        None => {
+            return ExtractionResult {
+                queries,
+                skipped_files: vec![SkippedResult::new(
+                    path,
+                    SkipCategory::Resolve,
+                    "Unable to find the Cargo.toml for this file".to_string(),
+                )],
+            }
+        }
+    };
+
+    for query_path in query_paths {
+        let full_path = owner.root.join(&query_path);
+        let canonical_path = full_path
+            .canonicalize()
+            .unwrap_or_else(|_| full_path.clone());
+
+        if !seen_query_paths.insert(canonical_path) {
+            continue;
+        }
+
+        match read_to_string(&full_path) {
+            Ok(query) => {
+                let source = DocumentSource {
+                    kind: Some("rust".to_string()),
+                    path: Some(full_path.display().to_string()),
+                    crate_name: Some(owner.name.clone()),
+                    ..Default::default()
+                };
+
+                queries.push(source.annotate(&query));
+            }
+            Err(e) => skipped_files.push(SkippedResult::new(
+                path,
+                SkipCategory::Io,
+                format!("Unable to read {}: {}", query_path, e),
+            )),
+        }
+    }
+
+    ExtractionResult {
+        queries,
+        skipped_files,
+    }
+}
@@ -0,0 +1,258 @@
+use crate::error::{Error, Result};
+use lazy_static::lazy_static;
+use native_tls::{Certificate, TlsConnector};
+use regex::{Captures, Regex};
+use serde_json::{json, Value as Json};
+use std::env;
+use std::fs::read;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use ureq::{Agent, AgentBuilder};
+
+lazy_static! {
+    static ref ENV_VAR_RE: Regex = Regex::new(r"\$\{(\w+)\}|\$(\w+)").unwrap();
+}
+
+/// The introspection query sent by graphql-js' `getIntrospectionQuery` with
+/// its default options. `specifiedByURL` is left out like there, it's named
+/// `specifiedByUrl` before graphql-js 16 and unknown before 15, and
+/// validation would reject the whole query on those servers.
+pub const INTROSPECTION_QUERY: &str = r#"
+query IntrospectionQuery {
+  __schema {
+    queryType { name }
+    mutationType { name }
+    subscriptionType { name }
+    types {
+      ...FullType
+    }
+    directives {
+      name
+      description
+      locations
+      args {
+        ...InputValue
+      }
+    }
+  }
+}
+
+fragment FullType on __Type {
+  kind
+  name
+  description
+  fields(includeDeprecated: true) {
+    name
+    description
+    args {
+      ...InputValue
+    }
+    type {
+      ...TypeRef
+    }
+    isDeprecated
+    deprecationReason
+  }
+  inputFields {
+    ...InputValue
+  }
+  interfaces {
+    ...TypeRef
+  }
+  enumValues(includeDeprecated: true) {
+    name
+    description
+    isDeprecated
+    deprecationReason
+  }
+  possibleTypes {
+    ...TypeRef
+  }
+}
+
+fragment InputValue on __InputValue {
+  name
+  description
+  type { ...TypeRef }
+  defaultValue
+}
+
+fragment TypeRef on __Type {
+  kind
+  name
+  ofType {
+    kind
+    name
+    ofType {
+      kind
+      name
+      ofType {
+        kind
+        name
+        ofType {
+          kind
+          name
+          ofType {
+            kind
+            name
+            ofType {
+              kind
+              name
+              ofType {
+                kind
+                name
+              }
+            }
+          }
+        }
+      }
+    }
+  }
+}
+"#;
+
+#[derive(Debug, Clone, Default)]
+pub struct FetchOptions {
+    /// Extra headers sent with the introspection query, like `Authorization`
+    pub headers: Vec<(String, String)>,
+    /// Applies to the whole request, from connecting to reading the response
+    pub timeout: Option<Duration>,
+    /// Accept invalid certificates and host names, for local endpoints
+    pub insecure: bool,
+    /// PEM certificate to trust on top of the system ones
+    pub ca_certificate: Option<PathBuf>,
+}
+
+pub fn is_url(input: &str) -> bool {
+    input.starts_with("http://") || input.starts_with("https://")
+}
+
+/// Parses a `Name: value` header, `$VAR` and `${VAR}` in the value are
+/// replaced with environment variables so tokens don't have to be written
+/// in scripts.
+pub fn parse_header(header: &str) -> Result<(String, String)> {
+    let mut parts = header.splitn(2, ':');
+
+    let name = parts.next().unwrap_or_default().trim();
+    let value = match parts.next() {
+        Some(value) if !name.is_empty() => value.trim(),
+        _ => {
+            return Err(Error::InvalidHeader(format!(
+                "{}, expected the Name: value format",
+                header
+            )))
+        }
+    };
+
+    let mut missing: Option<String> = None;
+
+    let value = ENV_VAR_RE.replace_all(value, |captures: &Captures| {
+        let var = captures
+            .get(1)
+            .or_else(|| captures.get(2))
+            .unwrap()
+            .as_str();
+
+        env::var(var).unwrap_or_else(|_| {
+            missing = Some(var.to_string());
+            String::new()
+        })
+    });
+
+    match missing {
+        Some(var) => Err(Error::InvalidHeader(format!(
+            "{} uses {} which isn't set",
+            name, var
+        ))),
+        None => Ok((name.to_string(), value.into_owned())),
+    }
+}
+
+fn fetch_error(url: &str, reason: String) -> Error {
+    Error::Fetch {
+        url: url.to_string(),
+        reason,
+    }
+}
+
+fn build_agent(url: &str, options: &FetchOptions) -> Result<Agent> {
+    let mut tls = TlsConnector::builder();
+
+    if options.insecure {
+        tls.danger_accept_invalid_certs(true)
+            .danger_accept_invalid_hostnames(true);
+    }
+
+    if let Some(path) = &options.ca_certificate {
+        let pem = read(path).map_err(|e| Error::io(path, e))?;
+        let certificate =
+            Certificate::from_pem(&pem).map_err(|e| fetch_error(url, e.to_string()))?;
+
+        tls.add_root_certificate(certificate);
+    }
+
+    let tls = tls.build().map_err(|e| fetch_error(url, e.to_string()))?;
+
+    let mut agent = AgentBuilder::new().tls_connector(Arc::new(tls));
+
+    if let Some(timeout) = options.timeout {
+        agent = agent.timeout(timeout);
+    }
+
+    Ok(agent.build())
+}
+
+/// Runs the introspection query against `url` and returns the response
+/// body, responses with errors and no data are reported as errors.
+pub fn fetch_introspection(url: &str, options: &FetchOptions) -> Result<String> {
+    let mut request = build_agent(url, options)?.post(url);
+
+    for (name, value) in &options.headers {
+        request = request.set(name, value);
+    }
+
+    let body = json!({
+        "operationName": "IntrospectionQuery",
+        "query": INTROSPECTION_QUERY,
+    });
+
+    let response = match request.send_json(body) {
+        Ok(response) => response,
+        Err(ureq::Error::Status(status, response)) => {
+            let body = response.into_string().unwrap_or_default();
+
+            return Err(fetch_error(
+                url,
+                format!("HTTP {}: {}", status, body.trim()),
+            ));
+        }
+        Err(e) => return Err(fetch_error(url, e.to_string())),
+    };
+
+    let body = response
+        .into_string()
+        .map_err(|e| fetch_error(url, e.to_string()))?;
+
+    let json: Json = serde_json::from_str(&body).map_err(|e| fetch_error(url, e.to_string()))?;
+
+    if json["data"].is_null() {
+        let messages: Vec<&str> = json["errors"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|error| error["message"].as_str())
+            .collect();
+
+        return Err(fetch_error(
+            url,
+            if messages.is_empty() {
+                "The response doesn't have any data".to_string()
+            } else {
+                messages.join(", ")
+            },
+        ));
+    }
+
+    Ok(body)
+}
@@ -0,0 +1,120 @@
+use crate::error::{Error, Result};
+use crate::find_files;
+use crate::introspection::{introspection_to_sdl, is_introspection};
+use crate::schema_fetcher::{fetch_introspection, is_url, FetchOptions};
+use crate::schema_stats::{build_schema, Schema, SchemaSource};
+use globwalk::GlobWalkerBuilder;
+use std::ffi::OsStr;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+const GLOB_CHARACTERS: &[char] = &['*', '?', '[', '{'];
+
+/// Expands a schema input to the files it refers to: globs are matched from
+/// their longest literal prefix and directories include every schema file
+/// in them.
+fn expand_schema_input(input: &str) -> Result<Vec<PathBuf>> {
+    let mut paths = if !input.contains(GLOB_CHARACTERS) {
+        let path = PathBuf::from(input);
+
+        if !path.is_dir() {
+            return Ok(vec![path]);
+        }
+
+        find_files(&path, "graphql,graphqls,json", &[])?
+    } else {
+        let mut base = PathBuf::new();
+        let mut pattern = PathBuf::new();
+
+        for component in Path::new(input).components() {
+            let is_glob = component
+                .as_os_str()
+                .to_string_lossy()
+                .contains(GLOB_CHARACTERS);
+
+            if is_glob || !pattern.as_os_str().is_empty() {
+                pattern.push(component);
+            } else {
+                base.push(component);
+            }
+        }
+
+        if base.as_os_str().is_empty() {
+            base.push(".");
+        }
+
+        GlobWalkerBuilder::from_patterns(&base, &[pattern.to_string_lossy()])
+            .follow_links(false)
+            .build()
+            .map_err(|e| Error::InvalidPattern(e.to_string()))?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.into_path())
+            .collect()
+    };
+
+    if paths.is_empty() {
+        return Err(Error::InvalidPattern(format!(
+            "{} doesn't match any schema file",
+            input
+        )));
+    }
+
+    // sorting keeps the order of the definitions stable between runs
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Reads a schema file, introspection results are converted to SDL.
+fn read_schema_file(path: PathBuf) -> Result<SchemaSource> {
+    let content = read_to_string(&path).map_err(|e| Error::io(&path, e))?;
+
+    let sdl = if path.extension() == Some(OsStr::new("json")) || is_introspection(&content) {
+        introspection_to_sdl(&content).map_err(|e| match e {
+            Error::InvalidIntrospection(reason) => {
+                Error::InvalidIntrospection(format!("{}: {}", path.display(), reason))
+            }
+            e => e,
+        })?
+    } else {
+        content
+    };
+
+    Ok(SchemaSource { path, sdl })
+}
+
+/// Loads a schema made of one or more SDL files, directories and glob
+/// patterns, with the type extensions of all of them applied. JSON files
+/// are read as introspection results.
+pub fn load_schema(inputs: &[String]) -> Result<Schema> {
+    load_schema_with_options(inputs, &FetchOptions::default())
+}
+
+/// Same as `load_schema`, URLs are introspected with the given `options`.
+pub fn load_schema_with_options(inputs: &[String], options: &FetchOptions) -> Result<Schema> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    let mut sources: Vec<SchemaSource> = Vec::new();
+
+    for input in inputs {
+        if is_url(input) {
+            let json = fetch_introspection(input, options)?;
+
+            sources.push(SchemaSource {
+                path: PathBuf::from(input),
+                sdl: introspection_to_sdl(&json)?,
+            });
+
+            continue;
+        }
+
+        for path in expand_schema_input(input)? {
+            if !paths.contains(&path) {
+                paths.push(path.clone());
+                sources.push(read_schema_file(path)?);
+            }
+        }
+    }
+
+    build_schema(&sources)
+}
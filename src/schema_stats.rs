@@ -1,3 +1,5 @@
+use crate::document::{filter_kinds, DocumentSource};
+use crate::error::{self, Error};
 use graphql_parser::query::parse_query;
 use graphql_parser::query::Definition::Fragment;
 use graphql_parser::query::Definition::Operation;
@@ -6,71 +8,623 @@ use graphql_parser::query::OperationDefinition;
 use graphql_parser::query::Selection::{Field, FragmentSpread, InlineFragment};
 use graphql_parser::query::SelectionSet;
 use graphql_parser::query::TypeCondition::On;
+use graphql_parser::query::Value as QueryValue;
 use graphql_parser::schema::{parse_schema, Definition, TypeDefinition};
-use graphql_parser::schema::{InterfaceType, ObjectType};
+use graphql_parser::schema::{Directive, DirectiveDefinition, DirectiveLocation, Value};
+use graphql_parser::schema::{
+    EnumType, InputObjectType, InputValue, InterfaceType, ObjectType, ScalarType, TypeExtension,
+    UnionType,
+};
+use graphql_parser::Pos;
+use lazy_static::lazy_static;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs::read_to_string;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
-#[derive(Debug)]
-struct Stats {
-    name: String,
-    age: u8,
+/// Reason used by `@deprecated` when none is given.
+pub const DEFAULT_DEPRECATION_REASON: &str = "No longer supported";
+
+/// Directives every schema defines, whether they can be used in documents
+/// and their arguments.
+pub(crate) const BUILT_IN_DIRECTIVES: &[(&str, bool, &[&str])] = &[
+    ("skip", true, &["if"]),
+    ("include", true, &["if"]),
+    ("deprecated", false, &["reason"]),
+    ("specifiedBy", false, &["url"]),
+];
+
+lazy_static! {
+    static ref PARSE_ERROR_POSITION_RE: Regex =
+        Regex::new(r"Parse error at (\d+):(\d+)\s*").unwrap();
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Stats {
+    pub types: HashMap<String, GraphQLType>,
+    /// Selections that couldn't be matched with the schema
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub directives: HashMap<String, StatsDirective>,
+    /// Usage tree of each root type, only built when `StatsOptions::tree`
+    /// is set
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tree: BTreeMap<String, UsageNode>,
+}
+
+impl Stats {
+    /// Arguments never supplied, as `Type.field(argument:)` coordinates.
+    pub fn unused_arguments(&self) -> Vec<String> {
+        let mut unused: Vec<String> = self
+            .types
+            .values()
+            .flat_map(|type_| {
+                type_.fields.values().flat_map(move |field| {
+                    field
+                        .arguments
+                        .iter()
+                        .filter(|(_, argument)| argument.count == 0)
+                        .map(move |(name, _)| format!("{}.{}({}:)", type_.name, field.name, name))
+                })
+            })
+            .collect();
+
+        unused.sort();
+
+        unused
+    }
+
+    /// Directives the schema defines for documents that are never used.
+    pub fn unused_directives(&self) -> Vec<String> {
+        let mut unused: Vec<String> = self
+            .directives
+            .values()
+            .filter(|directive| directive.defined && directive.executable && directive.count == 0)
+            .map(|directive| directive.name.clone())
+            .collect();
+
+        unused.sort();
+
+        unused
+    }
+
+    /// Directives used in documents that the schema doesn't define.
+    pub fn undefined_directives(&self) -> Vec<String> {
+        let mut undefined: Vec<String> = self
+            .directives
+            .values()
+            .filter(|directive| !directive.defined)
+            .map(|directive| directive.name.clone())
+            .collect();
+
+        undefined.sort();
+
+        undefined
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct StatsOptions {
+    /// Count fields selected by fragments, even if they are not used
+    pub include_fragments: bool,
+    /// Leave out documents extracted from these sources, for example `docs`
+    pub exclude_kinds: Vec<String>,
+    /// Record the operations, fragments and files selecting each field
+    pub attribution: bool,
+    /// Build the tree of paths selected from the root types
+    pub tree: bool,
+}
+
+/// Who selects a field, only recorded when `StatsOptions::attribution` is
+/// set.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Attribution {
+    /// Named operations selecting the field, directly or through fragments
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub operations: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub fragments: BTreeSet<String>,
+    /// Files the selections were extracted from, when the documents have
+    /// `# pal:` annotations
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub files: BTreeSet<String>,
+}
+
+impl Attribution {
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty() && self.fragments.is_empty() && self.files.is_empty()
+    }
+
+    fn record(&mut self, origin: &Origin) {
+        self.operations.extend(origin.operation.clone());
+        self.fragments.extend(origin.fragment.clone());
+        self.files.extend(origin.path.clone());
+    }
+}
+
+/// Selections at one response path from the root type, aliased fields are
+/// keyed by their alias like in the response. Fragments are expanded so
+/// their fields are counted at every path spreading them.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UsageNode {
+    pub r#type: String,
+    /// How many selections reach this path, for root types how many
+    /// operations
+    pub count: i32,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub children: BTreeMap<String, UsageNode>,
+}
+
+impl UsageNode {
+    fn new(type_name: &str) -> Self {
+        UsageNode {
+            r#type: type_name.to_string(),
+            count: 0,
+            children: BTreeMap::new(),
+        }
+    }
+
+    /// Adds the counts of `other` to this tree, `weight` times.
+    fn merge(&mut self, other: &UsageNode, weight: i32) {
+        self.count += other.count * weight;
+
+        for (name, child) in &other.children {
+            self.children
+                .entry(name.clone())
+                .or_insert_with(|| UsageNode::new(&child.r#type))
+                .merge(child, weight);
+        }
+    }
+
+    fn select(&mut self, field: &str, type_name: &str) -> &mut UsageNode {
+        let child = self
+            .children
+            .entry(field.to_string())
+            .or_insert_with(|| UsageNode::new(type_name));
+
+        child.count += 1;
+
+        child
+    }
+
+    /// Copy of the tree down to `max_depth` levels of fields, without the
+    /// paths selected less than `min_count` times.
+    pub fn pruned(&self, max_depth: Option<usize>, min_count: i32) -> UsageNode {
+        let children = match max_depth {
+            Some(0) => BTreeMap::new(),
+            _ => self
+                .children
+                .iter()
+                .filter(|(_, child)| child.count >= min_count)
+                .map(|(name, child)| {
+                    (
+                        name.clone(),
+                        child.pruned(max_depth.map(|depth| depth - 1), min_count),
+                    )
+                })
+                .collect(),
+        };
+
+        UsageNode {
+            r#type: self.r#type.clone(),
+            count: self.count,
+            children,
+        }
+    }
+}
+
+/// Where the selections being counted come from.
+#[derive(Clone, Default)]
+struct Origin {
+    operation: Option<String>,
+    /// Innermost fragment the selections are in
+    fragment: Option<String>,
+    path: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct StatsField {
     name: String,
     pub r#type: String,
+    /// Selections of the field on this type, for input fields how many
+    /// literals set them
+    pub count: i32,
+    /// Operations selecting the field on this type, however many times they
+    /// do
+    #[serde(default)]
+    pub operations: i32,
+    /// Aliases the field is selected under, with how many times
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub aliases: HashMap<String, i32>,
+    /// Selections of the field on the interfaces this type implements
+    #[serde(default)]
+    pub via_interface: i32,
+    /// Selections of the field on the types implementing this interface
+    #[serde(default)]
+    pub via_implementation: i32,
+    /// Variables whose type can set this input field
+    #[serde(default)]
+    pub via_variable: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Reason given by the `@deprecated` directive, when there is one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecation_reason: Option<String>,
+    /// Default value of input fields, as a GraphQL literal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_value: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub arguments: HashMap<String, StatsArgument>,
+    #[serde(default, skip_serializing_if = "Attribution::is_empty")]
+    pub used_by: Attribution,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StatsArgument {
+    pub r#type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_value: Option<String>,
+    /// How many selections of the field supply the argument
     pub count: i32,
+    pub literal: i32,
+    pub variable: i32,
+    /// Literal values of enum and boolean arguments, with how many times
+    /// they are passed
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub values: HashMap<String, i32>,
+}
+
+impl StatsField {
+    /// Selections reaching the field through an interface or one of its
+    /// implementations.
+    pub fn inherited(&self) -> i32 {
+        self.via_interface + self.via_implementation
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StatsDirective {
+    pub name: String,
+    /// Directives used in documents can be missing from the schema
+    pub defined: bool,
+    /// Whether the directive can be used in documents, and not only in the
+    /// schema
+    pub executable: bool,
+    pub count: i32,
+    /// Locations the directive is used at, like `FIELD` or `QUERY`, with how
+    /// many times
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub locations: HashMap<String, i32>,
+    /// Values passed to each argument, as GraphQL literals or `$variable`,
+    /// with how many times
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub arguments: HashMap<String, HashMap<String, i32>>,
+}
+
+impl StatsDirective {
+    fn new(name: String, defined: bool, executable: bool) -> Self {
+        StatsDirective {
+            name,
+            defined,
+            executable,
+            count: 0,
+            locations: HashMap::new(),
+            arguments: HashMap::new(),
+        }
+    }
+
+    pub fn from_definition(definition: DirectiveDefinition<String>) -> Self {
+        StatsDirective {
+            arguments: definition
+                .arguments
+                .into_iter()
+                .map(|argument| (argument.name, HashMap::new()))
+                .collect(),
+            ..StatsDirective::new(
+                definition.name,
+                true,
+                definition.locations.iter().any(DirectiveLocation::is_query),
+            )
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TypeKind {
+    Object,
+    Interface,
+    Union,
+    Enum,
+    InputObject,
+    Scalar,
+}
+
+impl TypeKind {
+    /// The keyword used to define types of this kind in SDL.
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            TypeKind::Object => "type",
+            TypeKind::Interface => "interface",
+            TypeKind::Union => "union",
+            TypeKind::Enum => "enum",
+            TypeKind::InputObject => "input",
+            TypeKind::Scalar => "scalar",
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GraphQLType {
     pub name: String,
+    pub kind: TypeKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// How many selected fields return this type
+    #[serde(default)]
+    pub count: i32,
+    /// Fields of objects and interfaces, input fields of input objects
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub fields: HashMap<String, StatsField>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interfaces: Vec<String>,
+    /// Object types implementing this interface
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub implementations: Vec<String>,
+    /// Member types of unions, with how many times they are selected
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub members: HashMap<String, i32>,
+    /// Values of enums, with how many times they are used in literals
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub values: HashMap<String, i32>,
+    /// Values of enums, with how many variables can pass them
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub values_via_variable: HashMap<String, i32>,
 }
 
 impl GraphQLType {
+    fn new(name: String, kind: TypeKind) -> Self {
+        GraphQLType {
+            name,
+            kind,
+            description: None,
+            count: 0,
+            fields: HashMap::new(),
+            interfaces: Vec::new(),
+            implementations: Vec::new(),
+            members: HashMap::new(),
+            values: HashMap::new(),
+            values_via_variable: HashMap::new(),
+        }
+    }
+
     pub fn from_object(obj: ObjectType<String>) -> Self {
-        let mut fields = HashMap::new();
+        GraphQLType {
+            description: obj.description,
+            fields: fields_from(obj.fields),
+            interfaces: obj.implements_interfaces,
+            ..GraphQLType::new(obj.name, TypeKind::Object)
+        }
+    }
+
+    pub fn from_interface(obj: InterfaceType<String>) -> Self {
+        GraphQLType {
+            description: obj.description,
+            fields: fields_from(obj.fields),
+            ..GraphQLType::new(obj.name, TypeKind::Interface)
+        }
+    }
+
+    pub fn from_union(obj: UnionType<String>) -> Self {
+        GraphQLType {
+            description: obj.description,
+            members: obj.types.into_iter().map(|name| (name, 0)).collect(),
+            ..GraphQLType::new(obj.name, TypeKind::Union)
+        }
+    }
+
+    pub fn from_enum(obj: EnumType<String>) -> Self {
+        GraphQLType {
+            description: obj.description,
+            values: obj
+                .values
+                .into_iter()
+                .map(|value| (value.name, 0))
+                .collect(),
+            ..GraphQLType::new(obj.name, TypeKind::Enum)
+        }
+    }
+
+    pub fn from_input_object(obj: InputObjectType<String>) -> Self {
+        GraphQLType {
+            description: obj.description,
+            fields: input_fields_from(obj.fields),
+            ..GraphQLType::new(obj.name, TypeKind::InputObject)
+        }
+    }
+
+    pub fn from_scalar(obj: ScalarType<String>) -> Self {
+        GraphQLType {
+            description: obj.description,
+            ..GraphQLType::new(obj.name, TypeKind::Scalar)
+        }
+    }
+
+    /// Returns what the extension adds to the type, with where it is defined.
+    pub fn from_extension(extension: TypeExtension<String>) -> (Self, Pos) {
+        match extension {
+            TypeExtension::Object(e) => (
+                GraphQLType {
+                    fields: fields_from(e.fields),
+                    interfaces: e.implements_interfaces,
+                    ..GraphQLType::new(e.name, TypeKind::Object)
+                },
+                e.position,
+            ),
+            TypeExtension::Interface(e) => (
+                GraphQLType {
+                    fields: fields_from(e.fields),
+                    ..GraphQLType::new(e.name, TypeKind::Interface)
+                },
+                e.position,
+            ),
+            TypeExtension::Union(e) => (
+                GraphQLType {
+                    members: e.types.into_iter().map(|name| (name, 0)).collect(),
+                    ..GraphQLType::new(e.name, TypeKind::Union)
+                },
+                e.position,
+            ),
+            TypeExtension::Enum(e) => (
+                GraphQLType {
+                    values: e.values.into_iter().map(|value| (value.name, 0)).collect(),
+                    ..GraphQLType::new(e.name, TypeKind::Enum)
+                },
+                e.position,
+            ),
+            TypeExtension::InputObject(e) => (
+                GraphQLType {
+                    fields: input_fields_from(e.fields),
+                    ..GraphQLType::new(e.name, TypeKind::InputObject)
+                },
+                e.position,
+            ),
+            TypeExtension::Scalar(e) => (GraphQLType::new(e.name, TypeKind::Scalar), e.position),
+        }
+    }
+
+    /// Adds the fields, interfaces, members and values of an extension to
+    /// the type, extensions can't redefine what the type already has.
+    pub fn extend(&mut self, extension: GraphQLType) -> Result<(), String> {
+        if extension.kind != self.kind {
+            return Err(format!(
+                "Unable to extend {} {} with extend {}",
+                self.kind.keyword(),
+                self.name,
+                extension.kind.keyword()
+            ));
+        }
+
+        for (name, field) in extension.fields {
+            if self.fields.contains_key(&name) {
+                return Err(format!("Field {}.{} is already defined", self.name, name));
+            }
+
+            self.fields.insert(name, field);
+        }
+
+        for interface in extension.interfaces {
+            if self.interfaces.contains(&interface) {
+                return Err(format!("{} already implements {}", self.name, interface));
+            }
+
+            self.interfaces.push(interface);
+        }
+
+        for (name, count) in extension.members {
+            if self.members.insert(name.clone(), count).is_some() {
+                return Err(format!("{} is already a member of {}", name, self.name));
+            }
+        }
+
+        for (name, count) in extension.values {
+            if self.values.insert(name.clone(), count).is_some() {
+                return Err(format!("Value {}.{} is already defined", self.name, name));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn deprecation_reason(directives: &[Directive<String>]) -> Option<String> {
+    let directive = directives
+        .iter()
+        .find(|directive| directive.name == "deprecated")?;
+
+    match directive
+        .arguments
+        .iter()
+        .find(|(name, _)| name == "reason")
+    {
+        Some((_, Value::String(reason))) => Some(reason.clone()),
+        _ => Some(DEFAULT_DEPRECATION_REASON.to_string()),
+    }
+}
 
-        for field in obj.fields {
-            fields.insert(
+fn fields_from(fields: Vec<graphql_parser::schema::Field<String>>) -> HashMap<String, StatsField> {
+    fields
+        .into_iter()
+        .map(|field| {
+            (
                 field.name.clone(),
                 StatsField {
+                    deprecation_reason: deprecation_reason(&field.directives),
                     name: field.name,
                     r#type: field.field_type.to_string(),
                     count: 0,
+                    operations: 0,
+                    aliases: HashMap::new(),
+                    via_interface: 0,
+                    via_implementation: 0,
+                    via_variable: 0,
+                    description: field.description,
+                    default_value: None,
+                    arguments: arguments_from(field.arguments),
+                    used_by: Attribution::default(),
                 },
-            );
-        }
-        GraphQLType {
-            name: obj.name,
-            fields: fields,
-        }
-    }
+            )
+        })
+        .collect()
+}
 
-    pub fn from_interface(obj: InterfaceType<String>) -> Self {
-        let mut fields = HashMap::new();
+fn arguments_from(arguments: Vec<InputValue<String>>) -> HashMap<String, StatsArgument> {
+    arguments
+        .into_iter()
+        .map(|argument| {
+            (
+                argument.name,
+                StatsArgument {
+                    r#type: argument.value_type.to_string(),
+                    default_value: argument.default_value.map(|value| value.to_string()),
+                    count: 0,
+                    literal: 0,
+                    variable: 0,
+                    values: HashMap::new(),
+                },
+            )
+        })
+        .collect()
+}
 
-        for field in obj.fields {
-            fields.insert(
+fn input_fields_from(fields: Vec<InputValue<String>>) -> HashMap<String, StatsField> {
+    fields
+        .into_iter()
+        .map(|field| {
+            (
                 field.name.clone(),
                 StatsField {
+                    deprecation_reason: deprecation_reason(&field.directives),
                     name: field.name,
-                    r#type: field.field_type.to_string(),
+                    r#type: field.value_type.to_string(),
                     count: 0,
+                    operations: 0,
+                    aliases: HashMap::new(),
+                    via_interface: 0,
+                    via_implementation: 0,
+                    via_variable: 0,
+                    description: field.description,
+                    default_value: field.default_value.map(|value| value.to_string()),
+                    arguments: HashMap::new(),
+                    used_by: Attribution::default(),
                 },
-            );
-        }
-        GraphQLType {
-            name: obj.name,
-            fields: fields,
-        }
-    }
+            )
+        })
+        .collect()
 }
 
+/// A query read from a traffic log, with how many requests sent it.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Query {
     pub query: String,
@@ -78,50 +632,525 @@ pub struct Query {
     pub count: i32,
 }
 
-fn get_schema_types(schema: String) -> HashMap<String, GraphQLType> {
+/// Names of the types operations start from.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct RootTypes {
+    pub query: Option<String>,
+    pub mutation: Option<String>,
+    pub subscription: Option<String>,
+}
+
+impl RootTypes {
+    /// Root types for schemas without a `schema { ... }` definition, using
+    /// the conventional names when the types exist.
+    fn conventional(types: &HashMap<String, GraphQLType>) -> Self {
+        let existing = |name: &str| Some(name.to_string()).filter(|name| types.contains_key(name));
+
+        RootTypes {
+            query: existing("Query"),
+            mutation: existing("Mutation"),
+            subscription: existing("Subscription"),
+        }
+    }
+
+    /// Returns the root type for `query`, `mutation` or `subscription`
+    /// operations.
+    pub fn get(&self, operation_type: &str) -> Result<&str, String> {
+        let root = match operation_type {
+            "query" => &self.query,
+            "mutation" => &self.mutation,
+            "subscription" => &self.subscription,
+            _ => &None,
+        };
+
+        root.as_deref().ok_or(format!(
+            "The schema doesn't define a {} root type",
+            operation_type
+        ))
+    }
+
+    /// Root type for an operation definition, with its selection set.
+    pub fn for_operation<'a, 'b>(
+        &self,
+        operation: &'b OperationDefinition<'a, &'a str>,
+    ) -> Result<(&str, &'b SelectionSet<'a, &'a str>), String> {
+        match operation {
+            OperationDefinition::Query(q) => Ok((self.get("query")?, &q.selection_set)),
+            OperationDefinition::Mutation(m) => Ok((self.get("mutation")?, &m.selection_set)),
+            OperationDefinition::Subscription(s) => {
+                Ok((self.get("subscription")?, &s.selection_set))
+            }
+            // this is when no operation this is passed, which means we have a query
+            OperationDefinition::SelectionSet(s) => Ok((self.get("query")?, s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Schema {
+    pub types: HashMap<String, GraphQLType>,
+    pub roots: RootTypes,
+    /// Directives defined by the schema, built-in ones included
+    pub directives: HashMap<String, StatsDirective>,
+}
+
+pub fn get_schema_types(schema: &str) -> error::Result<HashMap<String, GraphQLType>> {
+    get_schema(schema).map(|schema| schema.types)
+}
+
+/// An SDL document and the file it was read from.
+pub struct SchemaSource {
+    pub path: PathBuf,
+    pub sdl: String,
+}
+
+/// Formats a position in one of the schema files, the path is left out for
+/// schemas that don't come from a file.
+fn location(path: &Path, position: Pos) -> String {
+    if path.as_os_str().is_empty() {
+        position.to_string()
+    } else {
+        format!("{}:{}", path.display(), position)
+    }
+}
+
+fn syntax_error(path: &Path, error: graphql_parser::schema::ParseError) -> Error {
+    let message = error.to_string();
+
+    match PARSE_ERROR_POSITION_RE.captures(&message) {
+        Some(captures) => Error::SchemaSyntax {
+            path: path.to_path_buf(),
+            line: captures[1].parse().unwrap_or_default(),
+            column: captures[2].parse().unwrap_or_default(),
+            reason: message[captures.get(0).unwrap().end()..]
+                .trim()
+                .replace('\n', ", "),
+        },
+        None => Error::InvalidSchema(format!("{}: {}", path.display(), message)),
+    }
+}
+
+/// Parses an SDL schema, root types come from the `schema { ... }`
+/// definition when there is one.
+pub fn get_schema(schema: &str) -> error::Result<Schema> {
+    build_schema(&[SchemaSource {
+        path: PathBuf::new(),
+        sdl: schema.to_string(),
+    }])
+}
+
+/// Builds a single schema out of many SDL files, type extensions can
+/// extend types defined in any of them.
+pub fn build_schema(sources: &[SchemaSource]) -> error::Result<Schema> {
     let mut types: HashMap<String, GraphQLType> = HashMap::new();
-    let ast = parse_schema::<String>(&schema).expect("Unable to parse schema");
+    let mut type_locations: HashMap<String, String> = HashMap::new();
+    let mut roots: Option<RootTypes> = None;
+    let mut directives: HashMap<String, StatsDirective> = HashMap::new();
+    let mut extensions: Vec<(&Path, TypeExtension<String>)> = Vec::new();
+    let mut definitions: Vec<(&Path, Definition<String>)> = Vec::new();
 
-    for definition in ast.definitions {
-        match definition {
-            Definition::TypeDefinition(t) => match t {
-                TypeDefinition::Object(o) => {
-                    let type_ = GraphQLType::from_object(o);
+    for source in sources {
+        let ast = parse_schema::<String>(&source.sdl).map_err(|e| syntax_error(&source.path, e))?;
+
+        definitions.extend(
+            ast.definitions
+                .into_iter()
+                .map(|definition| (source.path.as_path(), definition)),
+        );
+    }
 
-                    types.insert(type_.name.clone(), type_);
+    for (path, definition) in definitions {
+        match definition {
+            Definition::SchemaDefinition(s) => {
+                if roots.is_some() {
+                    return Err(Error::InvalidSchema(format!(
+                        "Schema is defined more than once at {}",
+                        location(path, s.position)
+                    )));
                 }
-                TypeDefinition::Interface(i) => {
-                    let type_ = GraphQLType::from_interface(i);
 
-                    types.insert(type_.name.clone(), type_);
+                roots = Some(RootTypes {
+                    query: s.query,
+                    mutation: s.mutation,
+                    subscription: s.subscription,
+                });
+            }
+            Definition::TypeDefinition(t) => {
+                let position = match &t {
+                    TypeDefinition::Object(o) => o.position,
+                    TypeDefinition::Interface(i) => i.position,
+                    TypeDefinition::Union(u) => u.position,
+                    TypeDefinition::Enum(e) => e.position,
+                    TypeDefinition::InputObject(i) => i.position,
+                    TypeDefinition::Scalar(s) => s.position,
+                };
+                let type_ = match t {
+                    TypeDefinition::Object(o) => GraphQLType::from_object(o),
+                    TypeDefinition::Interface(i) => GraphQLType::from_interface(i),
+                    TypeDefinition::Union(u) => GraphQLType::from_union(u),
+                    TypeDefinition::Enum(e) => GraphQLType::from_enum(e),
+                    TypeDefinition::InputObject(i) => GraphQLType::from_input_object(i),
+                    TypeDefinition::Scalar(s) => GraphQLType::from_scalar(s),
+                };
+
+                let type_location = location(path, position);
+
+                if let Some(previous) = type_locations.get(&type_.name) {
+                    return Err(Error::InvalidSchema(format!(
+                        "Type {} is defined more than once, at {} and {}",
+                        type_.name, previous, type_location
+                    )));
                 }
-                _ => {}
-            },
-            _ => {}
+
+                type_locations.insert(type_.name.clone(), type_location);
+                types.insert(type_.name.clone(), type_);
+            }
+            // extensions can come before the type they extend
+            Definition::TypeExtension(e) => extensions.push((path, e)),
+            Definition::DirectiveDefinition(d) => {
+                directives.insert(d.name.clone(), StatsDirective::from_definition(d));
+            }
+        }
+    }
+
+    for (name, executable, arguments) in BUILT_IN_DIRECTIVES {
+        directives
+            .entry(name.to_string())
+            .or_insert_with(|| StatsDirective {
+                arguments: arguments
+                    .iter()
+                    .map(|argument| (argument.to_string(), HashMap::new()))
+                    .collect(),
+                ..StatsDirective::new(name.to_string(), true, *executable)
+            });
+    }
+
+    for (path, extension) in extensions {
+        let (extension, position) = GraphQLType::from_extension(extension);
+
+        match types.get_mut(&extension.name) {
+            Some(type_) => type_.extend(extension).map_err(|e| {
+                Error::InvalidSchema(format!("{} at {}", e, location(path, position)))
+            })?,
+            None => {
+                return Err(Error::InvalidSchema(format!(
+                    "Unable to extend undefined type {} at {}",
+                    extension.name,
+                    location(path, position)
+                )))
+            }
+        }
+    }
+
+    let implementations: Vec<(String, String)> = types
+        .values()
+        .flat_map(|type_| {
+            type_
+                .interfaces
+                .iter()
+                .map(move |interface| (interface.clone(), type_.name.clone()))
+        })
+        .collect();
+
+    for (interface, implementation) in implementations {
+        if let Some(interface) = types.get_mut(&interface) {
+            interface.implementations.push(implementation);
+        }
+    }
+
+    for type_ in types.values_mut() {
+        type_.implementations.sort();
+    }
+
+    let roots = match roots {
+        Some(roots) => roots,
+        None => RootTypes::conventional(&types),
+    };
+
+    for root in [&roots.query, &roots.mutation, &roots.subscription]
+        .iter()
+        .filter_map(|root| root.as_ref())
+    {
+        if !types.contains_key(root) {
+            return Err(Error::InvalidSchema(format!(
+                "Root type {} is not defined",
+                root
+            )));
+        }
+    }
+
+    Ok(Schema {
+        types,
+        roots,
+        directives,
+    })
+}
+
+/// Counts a type condition on one of the members of a union.
+fn count_union_member(schema: &mut HashMap<String, GraphQLType>, parent: &str, member: &str) {
+    if let Some(count) = schema
+        .get_mut(parent)
+        .and_then(|parent| parent.members.get_mut(member))
+    {
+        *count += 1;
+    }
+}
+
+/// Name of the type a field returns or an argument accepts, without the
+/// list and non null wrappers.
+pub(crate) fn named_type(type_: &str) -> String {
+    type_.replace("[", "").replace("]", "").replace("!", "")
+}
+
+/// Enum and boolean values of a literal, lists are counted value by value.
+fn literal_values<'a>(value: &QueryValue<'a, &'a str>, values: &mut Vec<String>) {
+    match value {
+        QueryValue::Boolean(value) => values.push(value.to_string()),
+        QueryValue::Enum(value) => values.push(value.to_string()),
+        QueryValue::List(items) => {
+            for item in items {
+                literal_values(item, values);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Counts the input fields and enum values set by a literal of type
+/// `type_name`, variables in it are counted from their definition instead.
+fn count_input_value<'a>(
+    schema: &mut HashMap<String, GraphQLType>,
+    type_name: &str,
+    value: &QueryValue<'a, &'a str>,
+) -> Result<(), String> {
+    match value {
+        QueryValue::List(items) => {
+            for item in items {
+                count_input_value(schema, type_name, item)?;
+            }
+        }
+        QueryValue::Enum(name) => {
+            let type_ = match schema.get_mut(type_name) {
+                Some(type_) if type_.kind == TypeKind::Enum => type_,
+                _ => return Ok(()),
+            };
+
+            match type_.values.get_mut(*name) {
+                Some(count) => *count += 1,
+                None => return Err(format!("Unable to get value {} in {}", name, type_name)),
+            }
+        }
+        QueryValue::Object(fields) => {
+            // custom scalars like `JSON` can take object literals too
+            match schema.get(type_name) {
+                Some(type_) if type_.kind == TypeKind::InputObject => {}
+                _ => return Ok(()),
+            }
+
+            for (name, value) in fields {
+                let field_type = match schema
+                    .get_mut(type_name)
+                    .and_then(|type_| type_.fields.get_mut(*name))
+                {
+                    Some(field) => {
+                        field.count += 1;
+                        named_type(&field.r#type)
+                    }
+                    None => return Err(format!("Unable to get {} in {}", name, type_name)),
+                };
+
+                count_input_value(schema, &field_type, value)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Counts every input field and enum value a variable of type `type_name`
+/// could set, each type is only counted once per variable.
+fn count_variable_input(
+    schema: &mut HashMap<String, GraphQLType>,
+    type_name: &str,
+    visited: &mut Vec<String>,
+) {
+    if visited.iter().any(|visited| visited == type_name) {
+        return;
+    }
+
+    visited.push(type_name.to_string());
+
+    let field_types: Vec<String> = match schema.get_mut(type_name) {
+        Some(type_) if type_.kind == TypeKind::InputObject => type_
+            .fields
+            .values_mut()
+            .map(|field| {
+                field.via_variable += 1;
+                named_type(&field.r#type)
+            })
+            .collect(),
+        Some(type_) if type_.kind == TypeKind::Enum => {
+            for value in type_.values.keys() {
+                *type_.values_via_variable.entry(value.clone()).or_insert(0) += 1;
+            }
+
+            Vec::new()
+        }
+        _ => Vec::new(),
+    };
+
+    for field_type in field_types {
+        count_variable_input(schema, &field_type, visited);
+    }
+}
+
+/// Counts the arguments supplied to a field, telling literals from
+/// variables. Unknown arguments are added to `errors` and skipped, so the
+/// rest of the operation is still counted.
+fn count_arguments<'a>(
+    schema: &mut HashMap<String, GraphQLType>,
+    type_name: &str,
+    field_name: &str,
+    arguments: &[(&'a str, QueryValue<'a, &'a str>)],
+    errors: &mut Vec<String>,
+) -> Result<(), String> {
+    for (name, value) in arguments {
+        let argument_type = match schema[type_name].fields[field_name].arguments.get(*name) {
+            Some(argument) => named_type(&argument.r#type),
+            None => {
+                errors.push(format!(
+                    "Unable to get argument {} of {}.{}",
+                    name, type_name, field_name
+                ));
+
+                continue;
+            }
+        };
+
+        let mut values: Vec<String> = Vec::new();
+
+        let is_enum =
+            matches!(schema.get(&argument_type), Some(type_) if type_.kind == TypeKind::Enum);
+
+        if is_enum || argument_type == "Boolean" {
+            literal_values(value, &mut values);
+        }
+
+        let argument = schema
+            .get_mut(type_name)
+            .and_then(|type_| type_.fields.get_mut(field_name))
+            .and_then(|field| field.arguments.get_mut(*name))
+            .unwrap();
+
+        argument.count += 1;
+
+        if let QueryValue::Variable(_) = value {
+            argument.variable += 1;
+        } else {
+            argument.literal += 1;
+        }
+
+        for value in values {
+            *argument.values.entry(value).or_insert(0) += 1;
+        }
+
+        count_input_value(schema, &argument_type, value)?;
+    }
+
+    Ok(())
+}
+
+/// Counts the directives applied at `location`, with the values of their
+/// arguments, directives the schema doesn't define are added as undefined.
+fn count_directives<'a>(
+    stats: &mut HashMap<String, StatsDirective>,
+    location: DirectiveLocation,
+    directives: &[Directive<'a, &'a str>],
+) {
+    for directive in directives {
+        let directive_stats = stats
+            .entry(directive.name.to_string())
+            .or_insert_with(|| StatsDirective::new(directive.name.to_string(), false, true));
+
+        directive_stats.count += 1;
+
+        *directive_stats
+            .locations
+            .entry(location.as_str().to_string())
+            .or_insert(0) += 1;
+
+        for (name, value) in &directive.arguments {
+            *directive_stats
+                .arguments
+                .entry(name.to_string())
+                .or_default()
+                .entry(value.to_string())
+                .or_insert(0) += 1;
         }
     }
+}
 
-    return types;
+/// Counts a field selected on an interface on the types implementing it,
+/// and a field selected on an object on the interfaces it implements.
+fn count_inherited_usage(
+    schema: &mut HashMap<String, GraphQLType>,
+    type_name: &str,
+    field_name: &str,
+) {
+    let (related, is_interface) = match schema.get(type_name) {
+        Some(type_) if type_.kind == TypeKind::Interface => (type_.implementations.clone(), true),
+        Some(type_) => (type_.interfaces.clone(), false),
+        None => return,
+    };
+
+    for name in related {
+        if let Some(field) = schema
+            .get_mut(&name)
+            .and_then(|type_| type_.fields.get_mut(field_name))
+        {
+            if is_interface {
+                field.via_interface += 1;
+            } else {
+                field.via_implementation += 1;
+            }
+        }
+    }
+}
+
+/// A fragment definition, with the file it was extracted from.
+struct ExtractedFragment<'a> {
+    definition: FragmentDefinition<'a, &'a str>,
+    path: Option<String>,
 }
 
 fn get_tree_for_selection_set<'a>(
     selection_set: SelectionSet<'a, &'a str>,
     root_type_name: &str,
-    schema: &mut HashMap<String, GraphQLType>,
-    fragments: &HashMap<String, FragmentDefinition<'a, &'a str>>,
+    stats: &mut Stats,
+    fragments: &HashMap<String, ExtractedFragment<'a>>,
+    origin: Option<&Origin>,
+    mut node: Option<&mut UsageNode>,
+    mut selected: Option<&mut HashSet<(String, String)>>,
 ) -> Result<(), String> {
     for item in selection_set.items {
         match item {
             Field(f) => {
+                count_directives(
+                    &mut stats.directives,
+                    DirectiveLocation::Field,
+                    &f.directives,
+                );
+
                 if f.name == "__typename" {
                     continue;
                 }
 
-                if !schema.contains_key(root_type_name) {
+                if !stats.types.contains_key(root_type_name) {
                     return Err(format!("Unable to get type {}", root_type_name));
                 }
 
-                let root_type = schema.get_mut(root_type_name).unwrap();
+                let root_type = stats.types.get_mut(root_type_name).unwrap();
 
                 if !root_type.fields.contains_key(f.name) {
                     return Err(format!("Unable to get {} in {}", f.name, root_type_name));
@@ -132,46 +1161,108 @@ fn get_tree_for_selection_set<'a>(
 
                 field.count += 1;
 
-                let field_type_name = field
-                    .r#type
-                    .clone()
-                    .replace("[", "")
-                    .replace("]", "")
-                    .replace("!", "");
+                if let Some(selected) = selected.as_deref_mut() {
+                    if selected.insert((root_type_name.to_string(), f.name.to_string())) {
+                        field.operations += 1;
+                    }
+                }
 
-                if let Err(err) =
-                    get_tree_for_selection_set(f.selection_set, &field_type_name, schema, fragments)
-                {
+                if let Some(alias) = f.alias {
+                    *field.aliases.entry(alias.to_string()).or_insert(0) += 1;
+                }
+
+                if let Some(origin) = origin {
+                    field.used_by.record(origin);
+                }
+
+                let field_type_name = named_type(&field.r#type);
+
+                if let Some(field_type) = stats.types.get_mut(&field_type_name) {
+                    field_type.count += 1;
+                }
+
+                count_inherited_usage(&mut stats.types, root_type_name, f.name);
+                count_arguments(
+                    &mut stats.types,
+                    root_type_name,
+                    f.name,
+                    &f.arguments,
+                    &mut stats.errors,
+                )?;
+
+                let child = node
+                    .as_deref_mut()
+                    .map(|node| node.select(f.alias.unwrap_or(f.name), &field_type_name));
+
+                if let Err(err) = get_tree_for_selection_set(
+                    f.selection_set,
+                    &field_type_name,
+                    stats,
+                    fragments,
+                    origin,
+                    child,
+                    selected.as_deref_mut(),
+                ) {
                     return Err(err);
                 }
             }
             InlineFragment(fragment) => {
-                let type_condition = fragment.type_condition.unwrap();
-
-                match type_condition {
-                    On(type_name) => {
-                        if let Err(err) = get_tree_for_selection_set(
-                            fragment.selection_set,
-                            &type_name,
-                            schema,
-                            fragments,
-                        ) {
-                            return Err(err);
-                        }
-                    }
-                }
+                count_directives(
+                    &mut stats.directives,
+                    DirectiveLocation::InlineFragment,
+                    &fragment.directives,
+                );
+
+                // fragments without a type condition are on the parent type
+                let type_name = match fragment.type_condition {
+                    Some(On(type_name)) => type_name.to_string(),
+                    None => root_type_name.to_string(),
+                };
+
+                count_union_member(&mut stats.types, root_type_name, &type_name);
+
+                get_tree_for_selection_set(
+                    fragment.selection_set,
+                    &type_name,
+                    stats,
+                    fragments,
+                    origin,
+                    node.as_deref_mut(),
+                    selected.as_deref_mut(),
+                )?;
             }
             FragmentSpread(fragment_spread) => {
+                count_directives(
+                    &mut stats.directives,
+                    DirectiveLocation::FragmentSpread,
+                    &fragment_spread.directives,
+                );
+
                 if fragments.contains_key(fragment_spread.fragment_name) {
-                    let fragment = &fragments[fragment_spread.fragment_name];
+                    let ExtractedFragment {
+                        definition: fragment,
+                        path,
+                    } = &fragments[fragment_spread.fragment_name];
+
+                    // selections of the fragment are written in its own file
+                    let fragment_origin = origin.map(|origin| Origin {
+                        fragment: Some(fragment.name.to_string()),
+                        path: path.clone(),
+                        ..origin.clone()
+                    });
 
                     match &fragment.type_condition {
                         On(type_name) => {
+                            count_union_member(&mut stats.types, root_type_name, type_name);
+
                             if let Err(err) = get_tree_for_selection_set(
                                 fragment.selection_set.clone(),
                                 &type_name,
-                                schema,
+                                stats,
                                 fragments,
+                                fragment_origin.as_ref(),
+                                node.as_deref_mut(),
+                                selected.as_deref_mut(),
                             ) {
                                 return Err(err);
                             }
@@ -187,90 +1278,236 @@ fn get_tree_for_selection_set<'a>(
 
 fn update_usages_for_operation<'a>(
     operation: OperationDefinition<'a, &'a str>,
-    schema: &mut HashMap<String, GraphQLType>,
-    fragments: &HashMap<String, FragmentDefinition<'a, &'a str>>,
+    path: Option<String>,
+    roots: &RootTypes,
+    stats: &mut Stats,
+    fragments: &HashMap<String, ExtractedFragment<'a>>,
+    options: &StatsOptions,
+    weight: i32,
 ) -> Result<(), String> {
-    match operation {
-        OperationDefinition::Query(q) => {
-            return get_tree_for_selection_set(q.selection_set, "Query", schema, fragments)
-        }
-        OperationDefinition::Mutation(m) => {
-            return get_tree_for_selection_set(m.selection_set, "Mutation", schema, fragments)
-        }
-        OperationDefinition::Subscription(s) => {
-            return get_tree_for_selection_set(s.selection_set, "Subscription", schema, fragments)
-        }
-        // this is when no operation this is passed, which means we have a query
-        OperationDefinition::SelectionSet(selection_set) => {
-            return get_tree_for_selection_set(selection_set, "Query", schema, fragments)
+    let (root_type_name, selection_set) = roots.for_operation(&operation)?;
+
+    let (name, variable_definitions, operation_directives, location) = match &operation {
+        OperationDefinition::Query(q) => (
+            q.name,
+            &q.variable_definitions[..],
+            &q.directives[..],
+            DirectiveLocation::Query,
+        ),
+        OperationDefinition::Mutation(m) => (
+            m.name,
+            &m.variable_definitions[..],
+            &m.directives[..],
+            DirectiveLocation::Mutation,
+        ),
+        OperationDefinition::Subscription(s) => (
+            s.name,
+            &s.variable_definitions[..],
+            &s.directives[..],
+            DirectiveLocation::Subscription,
+        ),
+        OperationDefinition::SelectionSet(_) => (None, &[][..], &[][..], DirectiveLocation::Query),
+    };
+
+    count_directives(&mut stats.directives, location, operation_directives);
+
+    for variable in variable_definitions {
+        let type_name = named_type(&variable.var_type.to_string());
+
+        count_variable_input(&mut stats.types, &type_name, &mut Vec::new());
+
+        // default values are literals, so their usage is proven
+        if let Some(value) = &variable.default_value {
+            count_input_value(&mut stats.types, &type_name, value)?;
         }
     }
+
+    let origin = Origin {
+        operation: name.map(str::to_string),
+        fragment: None,
+        path,
+    };
+
+    // the operation gets its own tree so it can be added with its weight
+    let mut operation_tree = UsageNode::new(root_type_name);
+    operation_tree.count = 1;
+
+    let result = get_tree_for_selection_set(
+        selection_set.clone(),
+        root_type_name,
+        stats,
+        fragments,
+        Some(&origin).filter(|_| options.attribution),
+        Some(&mut operation_tree).filter(|_| options.tree),
+        Some(&mut HashSet::new()),
+    );
+
+    if options.tree {
+        stats
+            .tree
+            .entry(root_type_name.to_string())
+            .or_insert_with(|| UsageNode::new(root_type_name))
+            .merge(&operation_tree, weight);
+    }
+
+    result
 }
 
 fn update_usages_for_fragment<'a>(
-    fragment: &FragmentDefinition<'a, &'a str>,
-    schema: &mut HashMap<String, GraphQLType>,
-    fragments: &HashMap<String, FragmentDefinition<'a, &'a str>>,
+    fragment: &ExtractedFragment<'a>,
+    stats: &mut Stats,
+    fragments: &HashMap<String, ExtractedFragment<'a>>,
+    options: &StatsOptions,
 ) -> Result<(), String> {
+    let ExtractedFragment {
+        definition: fragment,
+        path,
+    } = fragment;
+
+    let origin = Origin {
+        operation: None,
+        fragment: Some(fragment.name.to_string()),
+        path: path.clone(),
+    };
+
     match fragment.type_condition {
         On(type_name) => get_tree_for_selection_set(
             fragment.selection_set.clone(),
             &type_name,
-            schema,
+            stats,
             fragments,
+            Some(&origin).filter(|_| options.attribution),
+            None,
+            // standalone fragments aren't operations
+            None,
         ),
     }
 }
 
-fn extract_queries_and_fragments<'a>(
-    document: &'a str,
-) -> (
-    Vec<OperationDefinition<'a, &str>>,
-    HashMap<String, FragmentDefinition<'a, &str>>,
-) {
-    let ast = parse_query::<&str>(document).expect("Unable to parse queries");
+/// A document with how many times it's sent, 1 for static documents.
+type WeightedDocument = (DocumentSource, String, i32);
 
-    let mut operations: Vec<OperationDefinition<&str>> = Vec::new();
-    let mut fragments: HashMap<String, FragmentDefinition<&str>> = HashMap::new();
+type OperationsAndFragments<'a> = (
+    Vec<(OperationDefinition<'a, &'a str>, Option<String>, i32)>,
+    HashMap<String, ExtractedFragment<'a>>,
+);
 
-    for definition in ast.definitions {
-        match definition {
-            Operation(o) => {
-                operations.push(o);
-            }
-            Fragment(f) => {
-                fragments.insert(f.name.to_string(), f);
+/// Parses the documents one by one so every operation and fragment keeps
+/// the file it was extracted from, fragments can be spread from any of them.
+fn extract_queries_and_fragments(
+    documents: &[WeightedDocument],
+) -> error::Result<OperationsAndFragments<'_>> {
+    let mut operations: Vec<(OperationDefinition<&str>, Option<String>, i32)> = Vec::new();
+    let mut fragments: HashMap<String, ExtractedFragment> = HashMap::new();
+
+    for (source, document, weight) in documents {
+        let ast =
+            parse_query::<&str>(document).map_err(|e| Error::InvalidDocuments(e.to_string()))?;
+
+        for definition in ast.definitions {
+            match definition {
+                Operation(o) => {
+                    operations.push((o, source.path.clone(), *weight));
+                }
+                Fragment(f) => {
+                    fragments.insert(
+                        f.name.to_string(),
+                        ExtractedFragment {
+                            definition: f,
+                            path: source.path.clone(),
+                        },
+                    );
+                }
             }
         }
     }
 
-    return (operations, fragments);
+    Ok((operations, fragments))
 }
 
-pub fn generate_schema_stats(
-    schema_path: String,
-    queries_document: String,
-    include_fragments: bool,
-) -> HashMap<String, GraphQLType> {
-    let sdl = read_to_string(schema_path).expect("Unable to read schema");
+/// Counts how many times each field of the schema is selected in the
+/// documents, `documents` can contain the annotations written by
+/// `extract-queries`.
+pub fn schema_stats(schema: &str, documents: &str, options: &StatsOptions) -> error::Result<Stats> {
+    collect_stats(get_schema(schema)?, documents, options)
+}
 
-    let mut schema = get_schema_types(sdl);
+/// Same as `schema_stats`, for a schema that is already loaded.
+pub fn collect_stats(
+    schema: Schema,
+    documents: &str,
+    options: &StatsOptions,
+) -> error::Result<Stats> {
+    let documents: Vec<WeightedDocument> = filter_kinds(documents, &options.exclude_kinds)
+        .into_iter()
+        .map(|(source, document)| (source, document, 1))
+        .collect();
 
-    let (queries, fragments) = extract_queries_and_fragments(&queries_document);
+    collect_weighted_stats(schema, &documents, options)
+}
+
+/// Same as `collect_stats`, for queries read from a traffic log. Every
+/// distinct query is counted once in the field stats, the usage tree is
+/// weighted by how many requests sent each of them.
+pub fn collect_traffic_stats(
+    schema: Schema,
+    queries: &[Query],
+    options: &StatsOptions,
+) -> error::Result<Stats> {
+    let documents: Vec<WeightedDocument> = queries
+        .iter()
+        .map(|query| (DocumentSource::default(), query.query.clone(), query.count))
+        .collect();
+
+    collect_weighted_stats(schema, &documents, options)
+}
+
+fn collect_weighted_stats(
+    schema: Schema,
+    documents: &[WeightedDocument],
+    options: &StatsOptions,
+) -> error::Result<Stats> {
+    let Schema {
+        types,
+        roots,
+        directives,
+    } = schema;
+    let mut stats = Stats {
+        types,
+        errors: Vec::new(),
+        directives,
+        tree: BTreeMap::new(),
+    };
 
-    for operation in queries {
-        if let Err(err) = update_usages_for_operation(operation, &mut schema, &fragments) {
-            eprintln!("Error: {}", err)
+    let (queries, fragments) = extract_queries_and_fragments(documents)?;
+
+    for (operation, path, weight) in queries {
+        if let Err(err) = update_usages_for_operation(
+            operation, path, &roots, &mut stats, &fragments, options, weight,
+        ) {
+            stats.errors.push(err);
         }
     }
 
-    if include_fragments {
-        for (_, fragment) in &fragments {
-            if let Err(err) = update_usages_for_fragment(fragment, &mut schema, &fragments) {
-                eprintln!("Fragment error: {}", err)
-            }
+    for fragment in fragments.values() {
+        // unlike their fields, definitions are counted once however many
+        // times they are spread
+        count_directives(
+            &mut stats.directives,
+            DirectiveLocation::FragmentDefinition,
+            &fragment.definition.directives,
+        );
+
+        if !options.include_fragments {
+            continue;
+        }
+
+        if let Err(err) = update_usages_for_fragment(fragment, &mut stats, &fragments, options) {
+            stats
+                .errors
+                .push(format!("Fragment {}: {}", fragment.definition.name, err));
         }
     }
 
-    return schema;
+    Ok(stats)
 }
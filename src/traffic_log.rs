@@ -0,0 +1,41 @@
+use crate::schema_stats::Query;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+
+lazy_static! {
+    static ref QUERY_HASH_RE: Regex = Regex::new(r"query_hash=(?P<hash>[a-z0-9]+)").unwrap();
+}
+
+/// Reads a request from a log line like
+/// `... query_hash=abc123 ... query: { viewer { id } }`.
+fn get_query_from_log(line: &str) -> Option<Query> {
+    let mut parts = line.splitn(2, "query:");
+
+    let metadata = parts.next()?;
+    let query = parts.next()?.trim();
+
+    let hash = QUERY_HASH_RE.captures(metadata)?.name("hash")?;
+
+    Some(Query {
+        query: query.to_string(),
+        hash: hash.as_str().to_string(),
+        count: 0,
+    })
+}
+
+/// Groups the requests of a traffic log by query hash, each query counts
+/// how many requests sent it. Lines without a query are ignored.
+pub fn queries_from_log(log: &str) -> Vec<Query> {
+    let mut queries: HashMap<String, Query> = HashMap::new();
+
+    for query in log.lines().filter_map(get_query_from_log) {
+        queries.entry(query.hash.clone()).or_insert(query).count += 1;
+    }
+
+    let mut queries: Vec<Query> = queries.into_values().collect();
+
+    queries.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.hash.cmp(&b.hash)));
+
+    queries
+}
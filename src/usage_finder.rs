@@ -0,0 +1,480 @@
+use crate::document::{split_documents, DocumentSource};
+use crate::error::{Error, Result};
+use crate::schema_stats::{named_type, Schema, TypeKind};
+use graphql_parser::query::Definition::{Fragment, Operation};
+use graphql_parser::query::Selection::{Field, FragmentSpread, InlineFragment};
+use graphql_parser::query::TypeCondition::On;
+use graphql_parser::query::{
+    parse_query, Document, FragmentDefinition, OperationDefinition, SelectionSet, Value,
+};
+use graphql_parser::Pos;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+
+lazy_static! {
+    static ref COORDINATE_RE: Regex = Regex::new(r"^(\w+)(?:\.(\w+)(?:\((\w+):\))?)?$").unwrap();
+}
+
+/// A schema coordinate, like `User`, `User.email` or
+/// `Query.search(term:)`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Coordinate {
+    Type(String),
+    /// Fields, input fields and enum values
+    Member {
+        type_name: String,
+        name: String,
+    },
+    Argument {
+        type_name: String,
+        field: String,
+        argument: String,
+    },
+}
+
+/// A selection or value touching the coordinate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Usage {
+    /// Operation or fragment the usage is reached from, like `query Viewer`
+    /// or `fragment UserName`
+    pub definition: String,
+    /// Innermost fragment the usage is written in
+    pub fragment: Option<String>,
+    /// Path from the root type, like `Query.viewer.account.owner.email`
+    pub path: String,
+    pub file: Option<String>,
+    pub line: Option<usize>,
+}
+
+impl Coordinate {
+    pub fn parse(coordinate: &str) -> Result<Self> {
+        let captures = COORDINATE_RE
+            .captures(coordinate)
+            .ok_or_else(|| Error::InvalidSchemaCoordinate(coordinate.to_string()))?;
+
+        let type_name = captures[1].to_string();
+
+        Ok(match (captures.get(2), captures.get(3)) {
+            (None, _) => Coordinate::Type(type_name),
+            (Some(name), None) => Coordinate::Member {
+                type_name,
+                name: name.as_str().to_string(),
+            },
+            (Some(field), Some(argument)) => Coordinate::Argument {
+                type_name,
+                field: field.as_str().to_string(),
+                argument: argument.as_str().to_string(),
+            },
+        })
+    }
+
+    /// Makes sure the coordinate points to something the schema defines.
+    fn validate(&self, schema: &Schema) -> Result<()> {
+        let (type_name, member) = match self {
+            Coordinate::Type(type_name) => (type_name, None),
+            Coordinate::Member { type_name, name } => (type_name, Some(name)),
+            Coordinate::Argument {
+                type_name, field, ..
+            } => (type_name, Some(field)),
+        };
+
+        let type_ = schema
+            .types
+            .get(type_name)
+            .ok_or_else(|| Error::UnknownType(type_name.clone()))?;
+
+        let unknown_field = |field: String| Error::UnknownField {
+            type_name: type_name.clone(),
+            field,
+        };
+
+        match (member, self) {
+            (None, _) => Ok(()),
+            (Some(name), _) if type_.kind == TypeKind::Enum => match type_.values.get(name) {
+                Some(_) => Ok(()),
+                None => Err(unknown_field(name.clone())),
+            },
+            (Some(name), Coordinate::Argument { argument, .. }) => match type_.fields.get(name) {
+                Some(field) if field.arguments.contains_key(argument) => Ok(()),
+                _ => Err(unknown_field(format!("{}({}:)", name, argument))),
+            },
+            (Some(name), _) => match type_.fields.get(name) {
+                Some(_) => Ok(()),
+                None => Err(unknown_field(name.clone())),
+            },
+        }
+    }
+
+    /// Selections on types implementing the interface of the coordinate
+    /// touch it too.
+    fn matches_field(&self, parent_type: &str, field: &str, schema: &Schema) -> bool {
+        let (type_name, name) = match self {
+            Coordinate::Member { type_name, name } => (type_name, name),
+            Coordinate::Argument {
+                type_name, field, ..
+            } => (type_name, field),
+            Coordinate::Type(_) => return false,
+        };
+
+        let matches_type = parent_type == type_name
+            || matches!(schema.types.get(parent_type), Some(t) if t.interfaces.contains(type_name));
+
+        name == field && matches_type
+    }
+
+    fn is_type(&self, type_name: &str) -> bool {
+        matches!(self, Coordinate::Type(name) if name == type_name)
+    }
+
+    fn is_member(&self, parent_type: &str, member: &str) -> bool {
+        matches!(self, Coordinate::Member { type_name, name } if type_name == parent_type && name == member)
+    }
+}
+
+/// Where the selections being searched are written and reached from.
+#[derive(Clone)]
+struct Location<'d> {
+    definition: String,
+    fragment: Option<String>,
+    source: &'d DocumentSource,
+    path: String,
+}
+
+struct Search<'s, 'd, 'a> {
+    coordinate: &'s Coordinate,
+    schema: &'s Schema,
+    fragments: HashMap<&'a str, (&'d FragmentDefinition<'a, &'a str>, &'d DocumentSource)>,
+    /// Fragments spread by at least one operation
+    reached: HashSet<&'a str>,
+    usages: Vec<Usage>,
+}
+
+/// Line of a position in the file the document was extracted from. Text
+/// before the first annotation has no source, and starts the file.
+fn line_in_file(source: &DocumentSource, position: Pos) -> Option<usize> {
+    match (source.line, source.kind.as_deref()) {
+        (Some(line), _) => Some(line + position.line - 1),
+        (None, Some("graphql")) | (None, None) => Some(position.line),
+        (None, Some(_)) => None,
+    }
+}
+
+impl<'s, 'd, 'a> Search<'s, 'd, 'a> {
+    fn add_usage(&mut self, location: &Location, path: String, position: Pos) {
+        self.usages.push(Usage {
+            definition: location.definition.clone(),
+            fragment: location.fragment.clone(),
+            path,
+            file: location.source.path.clone(),
+            line: line_in_file(location.source, position),
+        });
+    }
+
+    /// Looks for input fields and enum values of the coordinate in a value
+    /// of type `type_name`.
+    fn search_value(
+        &mut self,
+        value: &Value<'a, &'a str>,
+        type_name: &str,
+        location: &Location,
+        path: &str,
+        position: Pos,
+    ) {
+        match value {
+            Value::Enum(name) if self.coordinate.is_member(type_name, name) => {
+                self.add_usage(location, format!("{}.{}", path, name), position);
+            }
+            Value::List(items) => {
+                for item in items {
+                    self.search_value(item, type_name, location, path, position);
+                }
+            }
+            Value::Object(fields) => {
+                for (name, value) in fields {
+                    let field_path = format!("{}.{}", path, name);
+
+                    if self.coordinate.is_member(type_name, name) {
+                        self.add_usage(location, field_path.clone(), position);
+                    }
+
+                    let field_type = self
+                        .schema
+                        .types
+                        .get(type_name)
+                        .and_then(|type_| type_.fields.get(*name))
+                        .map(|field| named_type(&field.r#type));
+
+                    if let Some(field_type) = field_type {
+                        self.search_value(value, &field_type, location, &field_path, position);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn search_selection_set(
+        &mut self,
+        selection_set: &'d SelectionSet<'a, &'a str>,
+        parent_type: &str,
+        location: &Location<'d>,
+        visiting: &mut Vec<&'a str>,
+    ) {
+        for item in &selection_set.items {
+            match item {
+                Field(f) => {
+                    let field = match self
+                        .schema
+                        .types
+                        .get(parent_type)
+                        .and_then(|type_| type_.fields.get(f.name))
+                    {
+                        Some(field) => field,
+                        // unknown fields are reported by the stats
+                        None => continue,
+                    };
+
+                    let field_type = named_type(&field.r#type);
+                    let path = format!("{}.{}", location.path, f.name);
+
+                    let argument_types: Vec<(&'a str, &'d Value<'a, &'a str>, String)> = f
+                        .arguments
+                        .iter()
+                        .filter_map(|(name, value)| {
+                            let argument = field.arguments.get(*name)?;
+
+                            Some((*name, value, named_type(&argument.r#type)))
+                        })
+                        .collect();
+
+                    let matches_field =
+                        self.coordinate
+                            .matches_field(parent_type, f.name, self.schema);
+
+                    let touched_path = match self.coordinate {
+                        Coordinate::Type(type_name) if *type_name == field_type => {
+                            Some(path.clone())
+                        }
+                        Coordinate::Member { .. } if matches_field => Some(path.clone()),
+                        Coordinate::Argument { argument, .. }
+                            if matches_field
+                                && f.arguments.iter().any(|(name, _)| name == argument) =>
+                        {
+                            Some(format!("{}({}:)", path, argument))
+                        }
+                        _ => None,
+                    };
+
+                    if let Some(touched_path) = touched_path {
+                        self.add_usage(location, touched_path, f.position);
+                    }
+
+                    for (name, value, argument_type) in argument_types {
+                        let argument_path = format!("{}({}:)", path, name);
+
+                        if self.coordinate.is_type(&argument_type) {
+                            self.add_usage(location, argument_path.clone(), f.position);
+                        }
+
+                        self.search_value(
+                            value,
+                            &argument_type,
+                            location,
+                            &argument_path,
+                            f.position,
+                        );
+                    }
+
+                    let field_location = Location {
+                        path,
+                        ..location.clone()
+                    };
+
+                    self.search_selection_set(
+                        &f.selection_set,
+                        &field_type,
+                        &field_location,
+                        visiting,
+                    );
+                }
+                InlineFragment(fragment) => {
+                    // fragments without a type condition are on the parent type
+                    let type_name = match &fragment.type_condition {
+                        Some(On(type_name)) => {
+                            if self.coordinate.is_type(type_name) {
+                                self.add_usage(location, location.path.clone(), fragment.position);
+                            }
+
+                            type_name
+                        }
+                        None => parent_type,
+                    };
+
+                    self.search_selection_set(
+                        &fragment.selection_set,
+                        type_name,
+                        location,
+                        visiting,
+                    );
+                }
+                FragmentSpread(spread) => {
+                    let (fragment, source) = match self.fragments.get(spread.fragment_name) {
+                        Some(fragment) => *fragment,
+                        None => continue,
+                    };
+
+                    // fragments spreading each other would never end
+                    if visiting.contains(&spread.fragment_name) {
+                        continue;
+                    }
+
+                    self.reached.insert(spread.fragment_name);
+                    visiting.push(spread.fragment_name);
+
+                    let fragment_location = Location {
+                        fragment: Some(fragment.name.to_string()),
+                        source,
+                        ..location.clone()
+                    };
+
+                    self.search_fragment(fragment, &fragment_location, visiting);
+
+                    visiting.pop();
+                }
+            }
+        }
+    }
+
+    fn search_fragment(
+        &mut self,
+        fragment: &'d FragmentDefinition<'a, &'a str>,
+        location: &Location<'d>,
+        visiting: &mut Vec<&'a str>,
+    ) {
+        let On(type_name) = fragment.type_condition;
+
+        if self.coordinate.is_type(type_name) {
+            self.add_usage(location, location.path.clone(), fragment.position);
+        }
+
+        self.search_selection_set(&fragment.selection_set, type_name, location, visiting);
+    }
+
+    fn search_operation(
+        &mut self,
+        operation: &'d OperationDefinition<'a, &'a str>,
+        source: &'d DocumentSource,
+    ) {
+        let (root_type, selection_set) = match self.schema.roots.for_operation(operation) {
+            Ok(root) => root,
+            // operations the schema can't run are reported by the stats
+            Err(_) => return,
+        };
+
+        let (keyword, name, variable_definitions) = match operation {
+            OperationDefinition::Query(q) => ("query", q.name, &q.variable_definitions[..]),
+            OperationDefinition::Mutation(m) => ("mutation", m.name, &m.variable_definitions[..]),
+            OperationDefinition::Subscription(s) => {
+                ("subscription", s.name, &s.variable_definitions[..])
+            }
+            OperationDefinition::SelectionSet(_) => ("query", None, &[][..]),
+        };
+
+        let location = Location {
+            definition: match name {
+                Some(name) => format!("{} {}", keyword, name),
+                None => keyword.to_string(),
+            },
+            fragment: None,
+            source,
+            path: root_type.to_string(),
+        };
+
+        for variable in variable_definitions {
+            let type_name = named_type(&variable.var_type.to_string());
+            let path = format!("${}", variable.name);
+
+            if self.coordinate.is_type(&type_name) {
+                self.add_usage(&location, path.clone(), variable.position);
+            }
+
+            if let Some(value) = &variable.default_value {
+                self.search_value(value, &type_name, &location, &path, variable.position);
+            }
+        }
+
+        self.search_selection_set(selection_set, root_type, &location, &mut Vec::new());
+    }
+}
+
+/// Finds every selection, argument and value touching `coordinate` in the
+/// documents, which can contain the annotations written by
+/// `extract-queries`. Fragments are searched from the operations spreading
+/// them, and on their own when no operation does.
+pub fn find_usages(
+    schema: &Schema,
+    documents: &str,
+    coordinate: &Coordinate,
+) -> Result<Vec<Usage>> {
+    coordinate.validate(schema)?;
+
+    let sources = split_documents(documents);
+
+    let parsed = sources
+        .iter()
+        .map(|(source, document)| {
+            parse_query::<&str>(document)
+                .map(|document| (source, document))
+                .map_err(|e| Error::InvalidDocuments(e.to_string()))
+        })
+        .collect::<Result<Vec<(&DocumentSource, Document<&str>)>>>()?;
+
+    let mut search = Search {
+        coordinate,
+        schema,
+        fragments: HashMap::new(),
+        reached: HashSet::new(),
+        usages: Vec::new(),
+    };
+
+    for (source, document) in &parsed {
+        for definition in &document.definitions {
+            if let Fragment(fragment) = definition {
+                search.fragments.insert(fragment.name, (fragment, *source));
+            }
+        }
+    }
+
+    for (source, document) in &parsed {
+        for definition in &document.definitions {
+            if let Operation(operation) = definition {
+                search.search_operation(operation, source);
+            }
+        }
+    }
+
+    let mut unreached: Vec<(&FragmentDefinition<&str>, &DocumentSource)> = search
+        .fragments
+        .iter()
+        .filter(|(name, _)| !search.reached.contains(*name))
+        .map(|(_, fragment)| *fragment)
+        .collect();
+
+    unreached.sort_by_key(|(fragment, _)| fragment.name);
+
+    for (fragment, source) in unreached {
+        let On(type_name) = fragment.type_condition;
+
+        let location = Location {
+            definition: format!("fragment {}", fragment.name),
+            fragment: Some(fragment.name.to_string()),
+            source,
+            path: type_name.to_string(),
+        };
+
+        search.search_fragment(fragment, &location, &mut vec![fragment.name]);
+    }
+
+    Ok(search.usages)
+}
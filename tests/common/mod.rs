@@ -0,0 +1,28 @@
+use std::fs::{create_dir_all, remove_dir_all, write};
+use std::path::PathBuf;
+
+/// The temporary directory `project` writes the files of `name` in.
+pub fn project_dir(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("graphql-pal-{}-{}", name, std::process::id()))
+}
+
+/// Writes the files in a fresh directory, so tests can extract or rewrite
+/// them, and returns their paths.
+pub fn project(name: &str, files: &[(&str, &str)]) -> Vec<PathBuf> {
+    let dir = project_dir(name);
+
+    let _ = remove_dir_all(&dir);
+    create_dir_all(&dir).unwrap();
+
+    files
+        .iter()
+        .map(|(file, content)| {
+            let path = dir.join(file);
+
+            create_dir_all(path.parent().unwrap()).unwrap();
+            write(&path, content).unwrap();
+
+            path
+        })
+        .collect()
+}
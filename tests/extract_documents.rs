@@ -0,0 +1,613 @@
+mod common;
+
+use common::{project, project_dir};
+use graphql_pal::document::split_documents;
+use graphql_pal::{extract_documents, ExtractionConfig, SkipCategory};
+use std::path::PathBuf;
+
+fn config() -> ExtractionConfig {
+    ExtractionConfig {
+        path: PathBuf::from("tests/fixtures/project"),
+        ..Default::default()
+    }
+}
+
+/// Writes the files with `common::project` and returns a config extracting
+/// from them.
+fn project_config(name: &str, files: &[(&str, &str)]) -> ExtractionConfig {
+    project(name, files);
+
+    ExtractionConfig {
+        path: project_dir(name),
+        ..Default::default()
+    }
+}
+
+fn kinds(documents: &[String]) -> Vec<String> {
+    let mut kinds = split_documents(&documents.join("\n"))
+        .into_iter()
+        .filter_map(|(source, _)| source.kind)
+        .collect::<Vec<String>>();
+
+    kinds.sort();
+    kinds
+}
+
+#[test]
+fn extracts_graphql_and_python_documents() {
+    let config = ExtractionConfig {
+        exclude: vec!["*.js".to_string()],
+        ..config()
+    };
+
+    let report = extract_documents(&config).unwrap();
+
+    assert_eq!(kinds(&report.documents), vec!["graphql", "python"]);
+    assert!(report
+        .documents
+        .iter()
+        .any(|document| document.contains("friends { id email }")));
+    assert!(report.skipped_files.is_empty());
+}
+
+#[test]
+fn extracts_docs_only_when_asked() {
+    let config = ExtractionConfig {
+        exclude: vec!["*.js".to_string()],
+        include_docs: true,
+        ..config()
+    };
+
+    let report = extract_documents(&config).unwrap();
+
+    assert_eq!(kinds(&report.documents), vec!["docs", "graphql", "python"]);
+}
+
+#[test]
+fn evaluates_javascript_templates() {
+    let report = extract_documents(&config()).unwrap();
+
+    let profile = report
+        .documents
+        .iter()
+        .find(|document| document.contains("ProfileQuery"))
+        .expect("ProfileQuery should be extracted");
+
+    assert!(profile.contains("kind=javascript"));
+    assert!(profile.contains("line=6"));
+    assert!(profile.contains("name"));
+}
+
+#[test]
+fn skips_files_that_cannot_be_parsed() {
+    let report = extract_documents(&config()).unwrap();
+
+    assert!(!report
+        .documents
+        .iter()
+        .any(|document| document.contains("BrokenQuery")));
+
+    let skipped = report
+        .skipped_files
+        .iter()
+        .find(|skipped| skipped.path.ends_with("Broken.js"))
+        .expect("Broken.js should be skipped");
+
+    assert_eq!(skipped.category, SkipCategory::Parse);
+    assert!(skipped.line.is_some());
+}
+
+#[test]
+fn skips_invalid_javascript_documents_with_their_line() {
+    let config = project_config(
+        "javascript-invalid",
+        &[(
+            "src/Invalid.js",
+            "import gql from \"graphql-tag\";\n\nexport const QUERY = gql`query InvalidQuery { viewer {`;\n",
+        )],
+    );
+
+    let report = extract_documents(&config).unwrap();
+
+    assert!(report.documents.is_empty());
+    assert_eq!(report.skipped_files.len(), 1);
+
+    let skipped = &report.skipped_files[0];
+
+    assert_eq!(skipped.category, SkipCategory::Parse);
+    assert!(skipped.reason.starts_with("Invalid GraphQL document: "));
+    assert_eq!(skipped.line, Some(3));
+}
+
+#[test]
+fn resolves_imported_javascript_modules() {
+    let config = project_config(
+        "javascript-modules",
+        &[
+            (
+                "src/Viewer.js",
+                "import gql from \"graphql-tag\";\nimport { NAME } from \"./fields\";\nconst { EMAIL } = require(\"./common\");\nconst ids = require(\"./ids\");\nconst URL = require(\"./urls\").URL;\n\nexport const VIEWER_QUERY = gql`query ViewerQuery { viewer { ${ids.ID} ${NAME} ${EMAIL} ${URL} ${require(\"./urls\").sizes.SMALL} } }`;\n",
+            ),
+            ("src/fields/index.js", "export const NAME = \"name\";\n"),
+            ("src/common.js", "module.exports = { EMAIL: \"email\" };\n"),
+            ("src/ids.ts", "exports.ID = \"id\";\n"),
+            (
+                "src/urls.js",
+                "exports.URL = \"url\";\nexports.sizes = { SMALL: \"small\" };\n",
+            ),
+            (
+                "src/Missing.js",
+                "import gql from \"graphql-tag\";\nimport { AVATAR } from \"./avatar\";\n\nexport const AVATAR_QUERY = gql`query AvatarQuery { viewer { ${AVATAR} } }`;\n",
+            ),
+        ],
+    );
+
+    let report = extract_documents(&config).unwrap();
+
+    assert!(report
+        .documents
+        .iter()
+        .any(|document| document
+            .contains("query ViewerQuery { viewer { id name email url small } }")));
+    assert_eq!(report.skipped_files.len(), 1);
+
+    let skipped = &report.skipped_files[0];
+
+    assert!(skipped.path.ends_with("Missing.js"));
+    assert_eq!(skipped.category, SkipCategory::Resolve);
+    assert_eq!(skipped.reason, "Unable to find module ./avatar for AVATAR");
+}
+
+#[test]
+fn skips_javascript_values_that_cannot_be_evaluated() {
+    let template = |expression: &str| {
+        format!(
+            "import gql from \"graphql-tag\";\nconst FIELDS = {{ name: \"name\" }};\nclass Viewer {{}}\nViewer.fields = FIELDS;\n\nexport const QUERY = gql`query ViewerQuery {{ viewer {{ ${{{}}} }} }}`;\n",
+            expression
+        )
+    };
+
+    let config = project_config(
+        "javascript-unsupported",
+        &[
+            ("src/Static.js", &template("Viewer.fields.name")),
+            ("src/Computed.js", &template("FIELDS[key]")),
+            (
+                "src/Conditional.js",
+                &template("key ? FIELDS.name : \"id\""),
+            ),
+        ],
+    );
+
+    let report = extract_documents(&config).unwrap();
+
+    assert!(report.documents.is_empty());
+
+    let mut skipped = report
+        .skipped_files
+        .iter()
+        .map(|skipped| {
+            (
+                PathBuf::from(&skipped.path)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string(),
+                skipped.category,
+                skipped.reason.as_str(),
+                skipped.line,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    skipped.sort();
+
+    assert_eq!(
+        skipped,
+        vec![
+            (
+                "Computed.js".to_string(),
+                SkipCategory::Unsupported,
+                "Unable to evaluate computed member expression",
+                Some(6)
+            ),
+            (
+                "Conditional.js".to_string(),
+                SkipCategory::Unsupported,
+                "Unsupported conditional expression",
+                Some(6)
+            ),
+            (
+                "Static.js".to_string(),
+                SkipCategory::Unsupported,
+                "Unable to evaluate Viewer.fields.name",
+                Some(6)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn resolves_rust_query_paths_from_the_crate_root() {
+    let struct_for = |name: &str| {
+        format!(
+            "#[derive(GraphQLQuery)]\n#[graphql(schema_path = \"schema.graphql\", query_path = \"queries/user.graphql\")]\npub struct {};\n",
+            name
+        )
+    };
+
+    let config = project_config(
+        "rust-query-paths",
+        &[
+            (
+                "Cargo.toml",
+                "[package]\nname = \"users-api\"\nversion = \"0.1.0\"\n",
+            ),
+            ("src/api/users.rs", &struct_for("UserQuery")),
+            ("src/api/profile.rs", &struct_for("ProfileQuery")),
+            (
+                "queries/user.graphql",
+                "query UserQuery { viewer { id } }\n",
+            ),
+        ],
+    );
+
+    let report = extract_documents(&config).unwrap();
+
+    // both structs and the file itself point to the same document
+    assert_eq!(report.documents.len(), 1);
+
+    let (source, document) = &split_documents(&report.documents[0])[0];
+
+    assert_eq!(source.kind.as_deref(), Some("rust"));
+    assert_eq!(source.crate_name.as_deref(), Some("users-api"));
+    assert!(source.path.as_ref().unwrap().ends_with("user.graphql"));
+    assert!(document.contains("UserQuery"));
+    assert!(report.skipped_files.is_empty());
+}
+
+#[test]
+fn skips_rust_query_paths_that_cannot_be_read() {
+    let config = project_config(
+        "rust-missing-query",
+        &[
+            ("Cargo.toml", "[package]\nname = \"users-api\"\n"),
+            (
+                "src/lib.rs",
+                "#[graphql(query_path = \"src/user.graphql\")]\npub struct UserQuery;\n",
+            ),
+        ],
+    );
+
+    let report = extract_documents(&config).unwrap();
+
+    assert!(report.documents.is_empty());
+    assert_eq!(report.skipped_files.len(), 1);
+    assert_eq!(report.skipped_files[0].category, SkipCategory::Io);
+}
+
+#[test]
+fn extracts_markdown_fences_with_their_line() {
+    let config = ExtractionConfig {
+        include_docs: true,
+        ..project_config(
+            "markdown-fences",
+            &[(
+                "README.md",
+                "# Users\n\n```graphql\nquery Users { users { id } }\n```\n\n~~~gql\n{ viewer { id } }\n~~~\n",
+            )],
+        )
+    };
+
+    let report = extract_documents(&config).unwrap();
+    let documents = split_documents(&report.documents.join("\n"));
+
+    assert_eq!(documents.len(), 2);
+    assert_eq!(documents[0].0.kind.as_deref(), Some("docs"));
+    assert_eq!(documents[0].0.line, Some(4));
+    assert_eq!(documents[1].0.line, Some(8));
+    assert!(report.skipped_files.is_empty());
+}
+
+#[test]
+fn skips_unclosed_markdown_fences() {
+    for (name, content) in &[
+        (
+            "markdown-unclosed",
+            "# Users\n\n```graphql\nquery Users { users { id } }\n",
+        ),
+        ("markdown-unclosed-at-end", "# Users\n\n```graphql"),
+    ] {
+        let config = ExtractionConfig {
+            include_docs: true,
+            ..project_config(name, &[("README.md", content)])
+        };
+
+        let report = extract_documents(&config).unwrap();
+
+        assert!(report.documents.is_empty());
+        assert_eq!(report.skipped_files.len(), 1);
+        assert_eq!(report.skipped_files[0].category, SkipCategory::Parse);
+        assert_eq!(report.skipped_files[0].line, Some(3));
+    }
+}
+
+#[test]
+fn keeps_python_documents_of_every_file_with_their_line() {
+    let config = project_config(
+        "python-dedup",
+        &[
+            (
+                "users.py",
+                "from gql import gql\n\nUSERS = gql(\"\"\"\n    query Users { users { id } }\n\"\"\")\nAGAIN = gql(\"query Users { users { id } }\")\nIDS = gql(\"{ users { id } }\")\n",
+            ),
+            ("teams.py", "QUERY = gql(\"query Users { users { id } }\")\n"),
+        ],
+    );
+
+    let report = extract_documents(&config).unwrap();
+    let mut documents: Vec<(String, usize, String)> = split_documents(&report.documents.join("\n"))
+        .into_iter()
+        .map(|(source, document)| {
+            let file = PathBuf::from(source.path.unwrap());
+            let file = file.file_name().unwrap().to_string_lossy().to_string();
+
+            (file, source.line.unwrap(), document.trim().to_string())
+        })
+        .collect();
+    documents.sort();
+
+    assert_eq!(
+        documents,
+        vec![
+            (
+                "teams.py".to_string(),
+                1,
+                "query Users { users { id } }".to_string()
+            ),
+            (
+                "users.py".to_string(),
+                4,
+                "query Users { users { id } }".to_string()
+            ),
+            ("users.py".to_string(), 7, "{ users { id } }".to_string()),
+        ]
+    );
+}
+
+fn python_documents(name: &str, source: &str) -> (Vec<String>, Vec<(SkipCategory, usize)>) {
+    let report = extract_documents(&project_config(name, &[("queries.py", source)])).unwrap();
+
+    let documents = split_documents(&report.documents.join("\n"))
+        .into_iter()
+        .map(|(_, document)| document.trim().to_string())
+        .collect();
+
+    let skipped = report
+        .skipped_files
+        .iter()
+        .map(|skipped| (skipped.category, skipped.line.unwrap()))
+        .collect();
+
+    (documents, skipped)
+}
+
+#[test]
+fn extracts_multiline_python_calls() {
+    let (documents, skipped) = python_documents(
+        "python-multiline",
+        "USERS = gql(
+    \"\"\"
+    query Users {
+      users { id }
+    }
+    \"\"\",
+    variables=None,
+)
+",
+    );
+
+    assert_eq!(documents, vec!["query Users {\n      users { id }\n    }"]);
+    assert!(skipped.is_empty());
+}
+
+#[test]
+fn evaluates_python_f_strings_and_concatenations() {
+    let (documents, skipped) = python_documents(
+        "python-strings",
+        "FIELDS = \"id\"
+NAME = \"name\"
+options = dict(
+FIELDS=\"email\",
+)
+
+VIEWER = gql(f\"query Viewer {{ viewer {{ {FIELDS} }} }}\")
+USER = gql(\"query User { \" + \"viewer { \" + NAME + \" } }\")
+ADJACENT = gql(\"query Adjacent \" \"{ viewer { id } }\")
+FORMATTED = gql(\"query Formatted { viewer { %s } }\" % FIELDS)
+",
+    );
+
+    assert_eq!(
+        documents,
+        vec![
+            "query Viewer { viewer { id } }",
+            "query User { viewer { name } }",
+            "query Adjacent { viewer { id } }",
+            "query Formatted { viewer { id } }",
+        ]
+    );
+    assert!(skipped.is_empty());
+}
+
+#[test]
+fn extracts_python_constants_marked_as_graphql() {
+    let (documents, skipped) = python_documents(
+        "python-marker",
+        "# graphql
+USER_QUERY = \"\"\"query User { viewer { id } }\"\"\"
+
+# GraphQL
+NOT_AN_ASSIGNMENT
+",
+    );
+
+    assert_eq!(documents, vec!["query User { viewer { id } }"]);
+    assert_eq!(skipped, vec![(SkipCategory::Parse, 5)]);
+}
+
+#[test]
+fn skips_python_expressions_that_cannot_be_evaluated() {
+    let (documents, skipped) = python_documents(
+        "python-unsupported",
+        "A = gql(build_query())
+B = gql(\"{ viewer { id } }\" * 2)
+C = gql(f\"{{ viewer {{ {fields.user} }} }}\")
+D = gql(\"{ viewer { \"
+",
+    );
+
+    assert!(documents.is_empty());
+    assert_eq!(
+        skipped,
+        vec![
+            (SkipCategory::Resolve, 1),
+            (SkipCategory::Unsupported, 2),
+            (SkipCategory::Unsupported, 3),
+            (SkipCategory::Parse, 4),
+        ]
+    );
+}
+
+#[test]
+fn checks_gatsby_fragment_spreads_across_the_project() {
+    let config = ExtractionConfig {
+        gatsby: true,
+        ..project_config(
+            "gatsby-fragments",
+            &[
+                (
+                    "src/pages/index.graphql",
+                    "query Index { site { ...SiteFields } }\n",
+                ),
+                (
+                    "src/fragments/site.graphql",
+                    "fragment SiteFields on Site { title }\n",
+                ),
+                (
+                    "src/pages/about.graphql",
+                    "query About { site { ...AuthorFields } }\n",
+                ),
+            ],
+        )
+    };
+
+    let report = extract_documents(&config).unwrap();
+
+    assert_eq!(report.documents.len(), 3);
+    assert_eq!(report.skipped_files.len(), 1);
+
+    let skipped = &report.skipped_files[0];
+
+    assert!(skipped.path.ends_with("about.graphql"));
+    assert_eq!(skipped.category, SkipCategory::Resolve);
+    assert_eq!(skipped.reason, "Unknown fragment AuthorFields");
+
+    let report = extract_documents(&ExtractionConfig {
+        gatsby: false,
+        ..config
+    })
+    .unwrap();
+
+    assert!(report.skipped_files.is_empty());
+}
+
+#[test]
+fn reports_fragments_defined_differently_in_two_files() {
+    let config = ExtractionConfig {
+        gatsby: true,
+        ..project_config(
+            "gatsby-duplicates",
+            &[
+                (
+                    "src/pages/index.graphql",
+                    "query Index { site { ...SiteFields } }\nfragment SiteFields on Site { title }\n",
+                ),
+                (
+                    "src/pages/blog.graphql",
+                    "query Blog { site { ...SiteFields } }\nfragment SiteFields on Site {\n  title\n}\n",
+                ),
+                (
+                    "src/pages/about.graphql",
+                    "query About { site { ...SiteFields } }\nfragment SiteFields on Site { description }\n",
+                ),
+            ],
+        )
+    };
+
+    let report = extract_documents(&config).unwrap();
+
+    // the same definition formatted differently isn't a duplicate
+    assert_eq!(report.skipped_files.len(), 1);
+    assert!(report.skipped_files[0].path.ends_with("about.graphql"));
+    assert!(report.skipped_files[0]
+        .reason
+        .starts_with("Fragment SiteFields is defined more than once (also in "));
+}
+
+#[test]
+fn checks_gatsby_fragment_spreads_in_javascript() {
+    let config = ExtractionConfig {
+        gatsby: true,
+        ..project_config(
+            "gatsby-javascript",
+            &[
+                (
+                    "src/pages/index.js",
+                    "import { graphql } from \"gatsby\";\n\nexport const query = graphql`\n  query Index { site { ...SiteFields } }\n`;\n",
+                ),
+                (
+                    "src/components/Header.js",
+                    "import { graphql, useStaticQuery } from \"gatsby\";\n\nexport const Header = () => {\n  const data = useStaticQuery(graphql`\n    query Header { site { ...SiteFields } }\n  `);\n};\n",
+                ),
+                (
+                    "src/fragments.js",
+                    "import { graphql } from \"gatsby\";\n\nexport const siteFields = graphql`\n  fragment SiteFields on Site { title }\n`;\n",
+                ),
+                (
+                    "src/pages/about.js",
+                    "import { graphql } from \"gatsby\";\n\nexport const query = graphql`\n  query About { site { ...AuthorFields } }\n`;\n",
+                ),
+                (
+                    "src/apollo/user.js",
+                    "import gql from \"graphql-tag\";\n\nexport const USER_FIELDS = gql`fragment UserFields on User { id }`;\n",
+                ),
+                (
+                    "src/apollo/Viewer.js",
+                    "import gql from \"graphql-tag\";\nimport { USER_FIELDS } from \"./user\";\n\nexport const VIEWER = gql`query Viewer { viewer { ...UserFields } } ${USER_FIELDS}`;\n",
+                ),
+                (
+                    "src/apollo/Friends.js",
+                    "import gql from \"graphql-tag\";\nimport { USER_FIELDS } from \"./user\";\n\nexport const FRIENDS = gql`query Friends { viewer { friends { ...UserFields } } } ${USER_FIELDS}`;\n",
+                ),
+            ],
+        )
+    };
+
+    let report = extract_documents(&config).unwrap();
+
+    assert_eq!(report.documents.len(), 7);
+    assert!(report
+        .documents
+        .iter()
+        .any(|document| document.contains("query Header")));
+
+    // fragments inlined in several documents aren't duplicates
+    assert_eq!(report.skipped_files.len(), 1);
+
+    let skipped = &report.skipped_files[0];
+
+    assert!(skipped.path.ends_with("about.js"));
+    assert_eq!(skipped.category, SkipCategory::Resolve);
+    assert_eq!(skipped.reason, "Unknown fragment AuthorFields");
+}
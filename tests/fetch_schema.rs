@@ -0,0 +1,135 @@
+use graphql_pal::schema_fetcher::parse_header;
+use graphql_pal::{fetch_introspection, load_schema_with_options, Error, FetchOptions};
+use std::fs::read_to_string;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Serves a single request with the given status and body, the request is
+/// returned once it has been answered.
+fn serve_once(status: &'static str, body: String) -> (String, JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/graphql", listener.local_addr().unwrap());
+
+    let handle = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut request = String::new();
+        let mut content_length = 0;
+
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+
+            if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+                content_length = length.trim().parse().unwrap();
+            }
+
+            request.push_str(&line);
+
+            if line == "\r\n" {
+                break;
+            }
+        }
+
+        let mut request_body = vec![0; content_length];
+        reader.read_exact(&mut request_body).unwrap();
+        request.push_str(&String::from_utf8(request_body).unwrap());
+
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        reader.get_mut().write_all(response.as_bytes()).unwrap();
+
+        request
+    });
+
+    (url, handle)
+}
+
+fn introspection() -> String {
+    read_to_string("tests/fixtures/introspection.json").unwrap()
+}
+
+#[test]
+fn sends_the_introspection_query_with_headers() {
+    std::env::set_var("PAL_TEST_TOKEN", "secret");
+
+    let (url, server) = serve_once("200 OK", introspection());
+    let options = FetchOptions {
+        headers: vec![parse_header("Authorization: Bearer ${PAL_TEST_TOKEN}").unwrap()],
+        ..Default::default()
+    };
+
+    let json = fetch_introspection(&url, &options).unwrap();
+    let request = server.join().unwrap();
+
+    assert_eq!(json, introspection());
+    assert!(request.starts_with("POST /graphql"));
+    assert!(request.contains("Authorization: Bearer secret"));
+    assert!(request.contains("IntrospectionQuery"));
+    assert!(!request.contains("specifiedBy"));
+}
+
+#[test]
+fn loads_a_schema_from_a_url() {
+    let (url, server) = serve_once("200 OK", introspection());
+
+    let schema = load_schema_with_options(&[url], &FetchOptions::default()).unwrap();
+    server.join().unwrap();
+
+    assert!(schema.types["User"].fields.contains_key("name"));
+    assert_eq!(schema.roots.query.as_deref(), Some("RootQuery"));
+}
+
+#[test]
+fn reports_http_and_graphql_errors() {
+    let (url, server) = serve_once("401 Unauthorized", "Missing token".to_string());
+
+    match fetch_introspection(&url, &FetchOptions::default()) {
+        Err(Error::Fetch { reason, .. }) => assert_eq!(reason, "HTTP 401: Missing token"),
+        result => panic!("Expected a fetch error, got {:?}", result),
+    }
+    server.join().unwrap();
+
+    let (url, server) = serve_once(
+        "200 OK",
+        r#"{"data": null, "errors": [{"message": "Introspection is disabled"}]}"#.to_string(),
+    );
+
+    match fetch_introspection(&url, &FetchOptions::default()) {
+        Err(Error::Fetch { reason, .. }) => assert_eq!(reason, "Introspection is disabled"),
+        result => panic!("Expected a fetch error, got {:?}", result),
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn times_out_on_slow_endpoints() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/graphql", listener.local_addr().unwrap());
+
+    let options = FetchOptions {
+        timeout: Some(Duration::from_millis(100)),
+        ..Default::default()
+    };
+
+    match fetch_introspection(&url, &options) {
+        Err(Error::Fetch { .. }) => (),
+        result => panic!("Expected a fetch error, got {:?}", result),
+    }
+}
+
+#[test]
+fn rejects_headers_with_unset_variables() {
+    match parse_header("Authorization: Bearer $PAL_UNSET_TOKEN") {
+        Err(Error::InvalidHeader(reason)) => assert!(reason.contains("PAL_UNSET_TOKEN")),
+        result => panic!("Expected an invalid header, got {:?}", result),
+    }
+
+    assert!(parse_header("Authorization").is_err());
+}
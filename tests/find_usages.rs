@@ -0,0 +1,144 @@
+use graphql_pal::{
+    extract_documents, find_usages, load_schema, Coordinate, Error, ExtractionConfig, Usage,
+};
+use std::path::PathBuf;
+
+fn usages(documents: &str, coordinate: &str) -> Vec<Usage> {
+    let schema = load_schema(&["tests/fixtures/introspection.json".to_string()]).unwrap();
+
+    find_usages(&schema, documents, &Coordinate::parse(coordinate).unwrap()).unwrap()
+}
+
+fn paths(usages: &[Usage]) -> Vec<&str> {
+    usages.iter().map(|usage| usage.path.as_str()).collect()
+}
+
+const DOCUMENTS: &str = "# pal: kind=javascript; path=src/users.js; line=10
+query Users($role: Role) {
+  users(filter: { role: $role }) { ...UserDetails }
+}
+# pal: kind=javascript; path=src/fragments.js; line=3
+fragment UserDetails on User {
+  id
+  email
+}
+";
+
+#[test]
+fn finds_fields_through_fragments() {
+    let usages = usages(DOCUMENTS, "User.email");
+
+    assert_eq!(paths(&usages), vec!["RootQuery.users.email"]);
+    assert_eq!(usages[0].definition, "query Users");
+    assert_eq!(usages[0].fragment.as_deref(), Some("UserDetails"));
+    assert_eq!(usages[0].file.as_deref(), Some("src/fragments.js"));
+    assert_eq!(usages[0].line, Some(5));
+}
+
+#[test]
+fn reports_lines_of_graphql_files() {
+    let documents = "# pal: kind=graphql; path=queries/user.graphql; line=1
+query User {
+  user(id: 1) {
+    email
+  }
+}
+";
+
+    let usages = usages(documents, "User.email");
+
+    assert_eq!(usages.len(), 1);
+    assert_eq!(usages[0].file.as_deref(), Some("queries/user.graphql"));
+    assert_eq!(usages[0].line, Some(3));
+}
+
+#[test]
+fn reports_lines_of_extracted_javascript_documents() {
+    let report = extract_documents(&ExtractionConfig {
+        path: PathBuf::from("tests/fixtures/project"),
+        ..Default::default()
+    })
+    .unwrap();
+    let documents = report
+        .documents
+        .iter()
+        .filter(|document| document.contains("kind=javascript"))
+        .cloned()
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let schema = load_schema(&["tests/fixtures/schema.graphql".to_string()]).unwrap();
+    let usages = find_usages(
+        &schema,
+        &documents,
+        &Coordinate::parse("User.email").unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(usages.len(), 1);
+    assert!(usages[0].file.as_deref().unwrap().ends_with("Profile.js"));
+    assert_eq!(usages[0].line, Some(10));
+}
+
+#[test]
+fn finds_types_in_variables_and_selections() {
+    let usages = usages(DOCUMENTS, "Role");
+
+    assert_eq!(paths(&usages), vec!["$role"]);
+
+    let usages = self::usages(DOCUMENTS, "User");
+
+    assert_eq!(paths(&usages), vec!["RootQuery.users", "RootQuery.users"]);
+}
+
+#[test]
+fn finds_enum_values_in_literals() {
+    let documents = "query Admins {
+  users(filter: { role: ADMIN }) { id }
+}
+query Guests {
+  users(filter: { role: GUEST }) { id }
+}
+";
+
+    let usages = usages(documents, "Role.ADMIN");
+
+    assert_eq!(paths(&usages), vec!["RootQuery.users(filter:).role.ADMIN"]);
+    assert_eq!(usages[0].definition, "query Admins");
+}
+
+#[test]
+fn finds_arguments() {
+    let documents = "query { users(first: 10) { id } }\nquery { users { id } }";
+
+    let usages = usages(documents, "RootQuery.users(first:)");
+
+    assert_eq!(paths(&usages), vec!["RootQuery.users(first:)"]);
+    assert_eq!(usages[0].definition, "query");
+}
+
+#[test]
+fn searches_fragments_no_operation_spreads() {
+    let documents = "fragment UserEmail on User { email }";
+
+    let usages = usages(documents, "User.email");
+
+    assert_eq!(paths(&usages), vec!["User.email"]);
+    assert_eq!(usages[0].definition, "fragment UserEmail");
+}
+
+#[test]
+fn rejects_invalid_coordinates() {
+    assert!(matches!(
+        Coordinate::parse("User.email.domain"),
+        Err(Error::InvalidSchemaCoordinate(_))
+    ));
+
+    let schema = load_schema(&["tests/fixtures/introspection.json".to_string()]).unwrap();
+    let coordinate = Coordinate::parse("User.phone").unwrap();
+
+    assert!(matches!(
+        find_usages(&schema, "", &coordinate),
+        Err(Error::UnknownField { .. })
+    ));
+}
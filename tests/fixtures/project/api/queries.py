@@ -0,0 +1,5 @@
+from gql import gql
+
+FIELDS = "id email"
+
+FRIENDS_QUERY = gql(f"query FriendsQuery {{ viewer {{ friends {{ {FIELDS} }} }} }}")
@@ -0,0 +1,11 @@
+import gql from "graphql-tag";
+
+export const BROKEN_QUERY = gql`
+  query BrokenQuery {
+    viewer {
+      id
+    }
+  }
+`;
+
+export default function Broken( {
@@ -0,0 +1,13 @@
+import gql from "graphql-tag";
+
+const NAME = "name";
+
+export const PROFILE_QUERY = gql`
+  query ProfileQuery {
+    viewer {
+      id
+      ${NAME}
+      email
+    }
+  }
+`;
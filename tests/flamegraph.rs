@@ -0,0 +1,75 @@
+use graphql_pal::flamegraph::{flamegraph_svg, folded_stacks};
+use graphql_pal::schema_stats::get_schema;
+use graphql_pal::traffic_log::queries_from_log;
+use graphql_pal::{collect_traffic_stats, schema_stats, StatsOptions};
+use std::fs::read_to_string;
+
+fn schema() -> String {
+    read_to_string("tests/fixtures/schema.graphql").unwrap()
+}
+
+fn tree_options() -> StatsOptions {
+    StatsOptions {
+        tree: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn folds_the_usage_tree() {
+    let documents = "query { viewer { name friends { name } } }\nquery { viewer { name } }";
+
+    let stats = schema_stats(&schema(), documents, &tree_options()).unwrap();
+
+    assert_eq!(
+        folded_stacks(&stats.tree),
+        vec![
+            "Query 2",
+            "Query;viewer 2",
+            "Query;viewer;friends 1",
+            "Query;viewer;friends;name 1",
+            "Query;viewer;name 2",
+        ]
+    );
+}
+
+#[test]
+fn weights_the_tree_with_traffic_logs() {
+    let log = "\
+2021-05-01 level=info query_hash=aaa query: query { viewer { name } }
+2021-05-01 level=info query_hash=bbb query: query { user(id: 1) { email } }
+2021-05-01 level=info healthcheck ok
+2021-05-01 level=info query_hash=aaa query: query { viewer { name } }
+";
+
+    let queries = queries_from_log(log);
+
+    assert_eq!(queries.len(), 2);
+    assert_eq!(queries[0].hash, "aaa");
+    assert_eq!(queries[0].count, 2);
+
+    let schema = get_schema(&schema()).unwrap();
+    let stats = collect_traffic_stats(schema, &queries, &tree_options()).unwrap();
+
+    assert_eq!(stats.tree["Query"].count, 3);
+    assert_eq!(
+        stats.tree["Query"].children["viewer"].children["name"].count,
+        2
+    );
+    assert_eq!(stats.tree["Query"].children["user"].count, 1);
+
+    // the field stats count each distinct query once
+    assert_eq!(stats.types["User"].fields["name"].count, 1);
+}
+
+#[test]
+fn renders_an_svg_flame_graph() {
+    let stats = schema_stats(&schema(), "{ viewer { name } }", &tree_options()).unwrap();
+
+    let svg = flamegraph_svg(&folded_stacks(&stats.tree), "Schema usage").unwrap();
+    let svg = String::from_utf8(svg).unwrap();
+
+    assert!(svg.contains("<svg"));
+    assert!(svg.contains("Schema usage"));
+    assert!(svg.contains("viewer"));
+}
@@ -0,0 +1,80 @@
+use graphql_pal::schema_stats::TypeKind;
+use graphql_pal::{collect_stats, introspection_to_sdl, load_schema, Error, StatsOptions};
+use std::fs::read_to_string;
+
+fn fixture() -> String {
+    read_to_string("tests/fixtures/introspection.json").unwrap()
+}
+
+#[test]
+fn converts_introspection_to_sdl() {
+    let sdl = introspection_to_sdl(&fixture()).unwrap();
+
+    assert!(sdl.contains("schema {\n  query: RootQuery\n}"));
+    assert!(sdl.contains("directive @cached(seconds: Int = 60) on FIELD_DEFINITION | OBJECT"));
+    assert!(sdl.contains("type User implements Node {"));
+    assert!(sdl.contains("username: String @deprecated(reason: \"Use `name` instead\")"));
+    assert!(sdl.contains("email: String @deprecated\n"));
+    assert!(sdl.contains("role: Role = MEMBER"));
+    assert!(sdl.contains("users(filter: UserFilter, first: Int = 10): [User!]!"));
+    assert!(
+        sdl.contains("scalar DateTime @specifiedBy(url: \"https://tools.ietf.org/html/rfc3339\")")
+    );
+    assert!(sdl.contains("\"Someone with an account\""));
+    assert!(!sdl.contains("__Schema"));
+    assert!(!sdl.contains("scalar String"));
+    assert!(!sdl.contains("directive @include"));
+}
+
+#[test]
+fn accepts_a_bare_schema() {
+    let json: serde_json::Value = serde_json::from_str(&fixture()).unwrap();
+
+    let from_data = introspection_to_sdl(&json["data"].to_string()).unwrap();
+    let from_schema = introspection_to_sdl(&json["data"]["__schema"].to_string()).unwrap();
+
+    assert_eq!(from_data, introspection_to_sdl(&fixture()).unwrap());
+    assert_eq!(from_schema, from_data);
+}
+
+#[test]
+fn loads_introspection_as_a_schema() {
+    let schema = load_schema(&["tests/fixtures/introspection.json".to_string()]).unwrap();
+
+    let user = &schema.types["User"];
+    assert_eq!(user.kind, TypeKind::Object);
+    assert_eq!(user.description.as_deref(), Some("Someone with an account"));
+    assert_eq!(
+        user.fields["username"].deprecation_reason.as_deref(),
+        Some("Use `name` instead")
+    );
+    assert_eq!(
+        user.fields["email"].deprecation_reason.as_deref(),
+        Some("No longer supported")
+    );
+    assert_eq!(
+        schema.types["UserFilter"].fields["role"]
+            .default_value
+            .as_deref(),
+        Some("MEMBER")
+    );
+    assert_eq!(schema.types["Role"].kind, TypeKind::Enum);
+    assert_eq!(schema.types["DateTime"].kind, TypeKind::Scalar);
+
+    let stats =
+        collect_stats(schema, "{ user(id: 1) { name } }", &StatsOptions::default()).unwrap();
+    assert_eq!(stats.types["User"].fields["name"].count, 1);
+}
+
+#[test]
+fn rejects_json_that_isnt_introspection() {
+    match introspection_to_sdl("{\"data\": {\"user\": null}}") {
+        Err(Error::InvalidIntrospection(_)) => (),
+        result => panic!("Expected an invalid introspection, got {:?}", result),
+    }
+
+    match introspection_to_sdl("{ not json") {
+        Err(Error::InvalidIntrospection(_)) => (),
+        result => panic!("Expected an invalid introspection, got {:?}", result),
+    }
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use common::project;
+use graphql_pal::operation_namer::name_operations;
+use graphql_pal::SkipCategory;
+use std::fs::read_to_string;
+
+#[test]
+fn names_anonymous_operations_after_their_component() {
+    let paths = project(
+        "namer-component",
+        &[(
+            "Profile.js",
+            "const Profile = () => {\n  const query = gql`query { viewer { id } }`;\n};\n",
+        )],
+    );
+
+    let result = name_operations(&paths, false);
+
+    assert_eq!(result.named.len(), 1);
+    assert_eq!(result.named[0].name, "ProfileQuery");
+    assert_eq!(result.named[0].line, 2);
+    assert!(read_to_string(&paths[0])
+        .unwrap()
+        .contains("gql`query ProfileQuery { viewer { id } }`"));
+    assert!(result.skipped_files.is_empty());
+}
+
+#[test]
+fn avoids_names_already_used() {
+    let paths = project(
+        "namer-collisions",
+        &[
+            (
+                "Profile.js",
+                "function Profile() {\n  return gql`{ viewer { id } }`;\n}\n",
+            ),
+            (
+                "profile.graphql",
+                "query ProfileQuery { viewer { name } }\n",
+            ),
+        ],
+    );
+
+    let result = name_operations(&paths, false);
+
+    assert_eq!(result.named[0].name, "ProfileQuery2");
+    assert!(read_to_string(&paths[0])
+        .unwrap()
+        .contains("gql`query ProfileQuery2 { viewer { id } }`"));
+}
+
+#[test]
+fn names_every_definition_of_a_file() {
+    let paths = project(
+        "namer-multiple",
+        &[(
+            "users.js",
+            "export const USERS = gql`
+  query { users { id } }
+  mutation { logout }
+`;
+export const TEAMS = gql`
+  ${TEAM_FIELDS}
+  { teams { ...TeamFields } }
+`;
+",
+        )],
+    );
+
+    let result = name_operations(&paths, false);
+    let mut names: Vec<&str> = result.named.iter().map(|n| n.name.as_str()).collect();
+    names.sort();
+
+    assert_eq!(
+        names,
+        vec!["UsersMutation", "UsersQuery", "UsersTeamsQuery"]
+    );
+
+    let content = read_to_string(&paths[0]).unwrap();
+
+    assert!(content.contains("query UsersQuery { users { id } }"));
+    assert!(content.contains("mutation UsersMutation { logout }"));
+    assert!(content.contains("query UsersTeamsQuery { teams { ...TeamFields } }"));
+}
+
+#[test]
+fn leaves_files_alone_on_dry_runs() {
+    let source = "const query = gql`{ viewer { id } }`;\n";
+    let paths = project("namer-dry-run", &[("viewer.js", source)]);
+
+    let result = name_operations(&paths, true);
+
+    assert_eq!(result.named[0].name, "ViewerQuery");
+    assert_eq!(read_to_string(&paths[0]).unwrap(), source);
+}
+
+#[test]
+fn skips_templates_that_cannot_be_mapped_back() {
+    // template raw values have their line endings normalized, so they don't
+    // match the source text anymore
+    let source = "const query = gql`\r\n  { viewer { id } }\r\n`;\r\n";
+    let paths = project("namer-mismatch", &[("viewer.js", source)]);
+
+    let result = name_operations(&paths, false);
+
+    assert!(result.named.is_empty());
+    assert_eq!(result.skipped_files.len(), 1);
+    assert_eq!(result.skipped_files[0].category, SkipCategory::Unsupported);
+    assert_eq!(result.skipped_files[0].line, Some(1));
+    assert_eq!(read_to_string(&paths[0]).unwrap(), source);
+}
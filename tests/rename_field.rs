@@ -0,0 +1,102 @@
+mod common;
+
+use common::project;
+use graphql_pal::field_renamer::{rename_field, FieldRename};
+use graphql_pal::schema_stats::{get_schema, Schema};
+use std::fs::read_to_string;
+use std::path::PathBuf;
+
+fn schema() -> Schema {
+    get_schema(&read_to_string("tests/fixtures/schema.graphql").unwrap()).unwrap()
+}
+
+fn rename(paths: &[PathBuf], coordinate: &str, new_name: &str, add_alias: bool) -> Vec<usize> {
+    let rename = FieldRename::from_coordinate(coordinate, new_name, add_alias).unwrap();
+    let result = rename_field(paths, &rename, &schema(), false).unwrap();
+
+    assert!(result.skipped_files.is_empty());
+
+    result.renamed.iter().map(|renamed| renamed.line).collect()
+}
+
+#[test]
+fn renames_selections() {
+    let paths = project(
+        "rename-plain",
+        &[(
+            "viewer.graphql",
+            "query Viewer {\n\tviewer {\n\t\tid name\n\t}\n}\n",
+        )],
+    );
+
+    assert_eq!(rename(&paths, "User.name", "fullName", false), vec![3]);
+    assert_eq!(
+        read_to_string(&paths[0]).unwrap(),
+        "query Viewer {\n\tviewer {\n\t\tid fullName\n\t}\n}\n"
+    );
+}
+
+#[test]
+fn keeps_the_response_shape_with_an_alias() {
+    let paths = project(
+        "rename-alias",
+        &[("viewer.graphql", "{ viewer { name displayName: name } }\n")],
+    );
+
+    assert_eq!(rename(&paths, "User.name", "fullName", true).len(), 2);
+    assert_eq!(
+        read_to_string(&paths[0]).unwrap(),
+        "{ viewer { name: fullName displayName: fullName } }\n"
+    );
+}
+
+#[test]
+fn renames_fields_of_interfaces_on_implementations() {
+    let paths = project(
+        "rename-interface",
+        &[(
+            "node.graphql",
+            "{ node(id: 1) { id ... on User { id name } } viewer { id } }\n",
+        )],
+    );
+
+    assert_eq!(rename(&paths, "Node.id", "uid", false).len(), 3);
+    assert_eq!(
+        read_to_string(&paths[0]).unwrap(),
+        "{ node(id: 1) { uid ... on User { uid name } } viewer { uid } }\n"
+    );
+}
+
+#[test]
+fn renames_fields_in_fragment_definitions() {
+    let paths = project(
+        "rename-fragment",
+        &[(
+            "user.graphql",
+            "fragment UserName on User { name }\nquery { viewer { ...UserName } }\n",
+        )],
+    );
+
+    assert_eq!(rename(&paths, "User.name", "fullName", false), vec![1]);
+    assert_eq!(
+        read_to_string(&paths[0]).unwrap(),
+        "fragment UserName on User { fullName }\nquery { viewer { ...UserName } }\n"
+    );
+}
+
+#[test]
+fn renames_fields_in_javascript_templates() {
+    let paths = project(
+        "rename-javascript",
+        &[(
+            "Viewer.js",
+            "const query = gql`\n  ${USER_FRAGMENT}\n  query Viewer { viewer { name } }\n`;\n",
+        )],
+    );
+
+    assert_eq!(rename(&paths, "User.name", "fullName", false), vec![3]);
+    assert_eq!(
+        read_to_string(&paths[0]).unwrap(),
+        "const query = gql`\n  ${USER_FRAGMENT}\n  query Viewer { viewer { fullName } }\n`;\n"
+    );
+}
@@ -0,0 +1,82 @@
+use graphql_pal::{collect_stats, load_schema, Error, StatsOptions};
+use std::path::PathBuf;
+
+fn inputs(inputs: &[&str]) -> Vec<String> {
+    inputs.iter().map(|input| input.to_string()).collect()
+}
+
+#[test]
+fn merges_multiple_files() {
+    let schema = load_schema(&inputs(&[
+        "tests/fixtures/domains/query.graphql",
+        "tests/fixtures/domains/team.graphql",
+        "tests/fixtures/domains/user.graphql",
+    ]))
+    .unwrap();
+
+    assert!(schema.types["Query"].fields.contains_key("viewer"));
+    assert!(schema.types["Query"].fields.contains_key("team"));
+}
+
+#[test]
+fn expands_globs_and_directories() {
+    let from_glob = load_schema(&inputs(&["tests/fixtures/domains/*.graphql"])).unwrap();
+    let from_directory = load_schema(&inputs(&["tests/fixtures/domains"])).unwrap();
+
+    assert_eq!(from_glob.types.len(), 3);
+    assert_eq!(from_directory.types.len(), 3);
+}
+
+#[test]
+fn collects_stats_for_a_loaded_schema() {
+    let schema = load_schema(&inputs(&["tests/fixtures/domains"])).unwrap();
+
+    let stats = collect_stats(
+        schema,
+        "{ team { name } viewer { team { name } } }",
+        &StatsOptions::default(),
+    )
+    .unwrap();
+
+    assert_eq!(stats.types["Team"].fields["name"].count, 2);
+    assert!(stats.errors.is_empty());
+}
+
+#[test]
+fn reports_syntax_errors_with_the_file_and_line() {
+    let result = load_schema(&inputs(&[
+        "tests/fixtures/domains",
+        "tests/fixtures/broken/schema.graphql",
+    ]));
+
+    match result {
+        Err(Error::SchemaSyntax { path, line, .. }) => {
+            assert_eq!(path, PathBuf::from("tests/fixtures/broken/schema.graphql"));
+            assert_eq!(line, 6);
+        }
+        _ => panic!("expected a syntax error"),
+    }
+}
+
+#[test]
+fn reports_types_defined_in_two_files() {
+    let result = load_schema(&inputs(&[
+        "tests/fixtures/domains/user.graphql",
+        "tests/fixtures/schema.graphql",
+    ]));
+
+    match result {
+        Err(Error::InvalidSchema(reason)) => assert_eq!(
+            reason,
+            "Type User is defined more than once, at tests/fixtures/domains/user.graphql:1:1 and tests/fixtures/schema.graphql:11:1"
+        ),
+        _ => panic!("expected an invalid schema error"),
+    }
+}
+
+#[test]
+fn returns_an_error_for_globs_without_matches() {
+    let result = load_schema(&inputs(&["tests/fixtures/domains/*.graphqls"]));
+
+    assert!(matches!(result, Err(Error::InvalidPattern(_))));
+}
@@ -0,0 +1,640 @@
+use graphql_pal::schema_stats::TypeKind;
+use graphql_pal::{schema_stats, Error, StatsOptions};
+use std::fs::read_to_string;
+
+fn schema() -> String {
+    read_to_string("tests/fixtures/schema.graphql").unwrap()
+}
+
+fn count(stats: &graphql_pal::Stats, type_name: &str, field: &str) -> i32 {
+    stats.types[type_name].fields[field].count
+}
+
+#[test]
+fn counts_selected_fields() {
+    let documents = "query { viewer { id name } }\nquery { user(id: 1) { id } }";
+
+    let stats = schema_stats(&schema(), documents, &StatsOptions::default()).unwrap();
+
+    assert_eq!(count(&stats, "Query", "viewer"), 1);
+    assert_eq!(count(&stats, "Query", "user"), 1);
+    assert_eq!(count(&stats, "User", "id"), 2);
+    assert_eq!(count(&stats, "User", "name"), 1);
+    assert_eq!(count(&stats, "User", "email"), 0);
+    assert!(stats.errors.is_empty());
+}
+
+#[test]
+fn propagates_usage_through_interfaces() {
+    let documents = "{ node(id: 1) { id ... on User { name } } }\n{ viewer { id } }";
+
+    let stats = schema_stats(&schema(), documents, &StatsOptions::default()).unwrap();
+
+    let node_id = &stats.types["Node"].fields["id"];
+    assert_eq!(node_id.count, 1);
+    assert_eq!(node_id.via_implementation, 1);
+
+    let user_id = &stats.types["User"].fields["id"];
+    assert_eq!(user_id.count, 1);
+    assert_eq!(user_id.via_interface, 1);
+    assert_eq!(user_id.inherited(), 1);
+
+    assert_eq!(count(&stats, "User", "name"), 1);
+    assert_eq!(stats.types["User"].fields["name"].inherited(), 0);
+    assert_eq!(stats.types["Node"].implementations, vec!["User"]);
+}
+
+#[test]
+fn counts_unused_fragments_only_when_asked() {
+    let documents = "fragment UserEmail on User { email }\nquery { viewer { id } }";
+
+    let stats = schema_stats(&schema(), documents, &StatsOptions::default()).unwrap();
+
+    assert_eq!(count(&stats, "User", "email"), 0);
+
+    let options = StatsOptions {
+        include_fragments: true,
+        ..Default::default()
+    };
+    let stats = schema_stats(&schema(), documents, &options).unwrap();
+
+    assert_eq!(count(&stats, "User", "email"), 1);
+}
+
+#[test]
+fn leaves_out_excluded_kinds() {
+    let documents = "# pal: kind=javascript; path=src/App.js\n\
+                     query { viewer { id } }\n\
+                     # pal: kind=docs; path=docs/README.md; line=3\n\
+                     query { viewer { name } }\n";
+
+    let options = StatsOptions {
+        exclude_kinds: vec!["docs".to_string()],
+        ..Default::default()
+    };
+    let stats = schema_stats(&schema(), documents, &options).unwrap();
+
+    assert_eq!(count(&stats, "User", "id"), 1);
+    assert_eq!(count(&stats, "User", "name"), 0);
+}
+
+#[test]
+fn reports_unknown_fields() {
+    let documents = "query { viewer { age } }";
+
+    let stats = schema_stats(&schema(), documents, &StatsOptions::default()).unwrap();
+
+    assert_eq!(stats.errors, vec!["Unable to get age in User".to_string()]);
+}
+
+#[test]
+fn returns_an_error_for_an_invalid_schema() {
+    let result = schema_stats("type Query {", "query { viewer }", &StatsOptions::default());
+
+    assert!(matches!(result, Err(Error::SchemaSyntax { line: 1, .. })));
+}
+
+#[test]
+fn returns_an_error_for_invalid_documents() {
+    let result = schema_stats(&schema(), "query {", &StatsOptions::default());
+
+    assert!(matches!(result, Err(Error::InvalidDocuments(_))));
+}
+
+const CUSTOM_ROOTS_SCHEMA: &str = "
+schema {
+  query: RootQuery
+  mutation: RootMutation
+}
+
+type RootQuery {
+  viewer: String
+}
+
+type RootMutation {
+  logout: Boolean
+}
+";
+
+#[test]
+fn uses_root_types_from_the_schema_definition() {
+    let documents = "query { viewer }\nmutation { logout }\n{ viewer }";
+
+    let stats = schema_stats(CUSTOM_ROOTS_SCHEMA, documents, &StatsOptions::default()).unwrap();
+
+    assert_eq!(count(&stats, "RootQuery", "viewer"), 2);
+    assert_eq!(count(&stats, "RootMutation", "logout"), 1);
+    assert!(stats.errors.is_empty());
+}
+
+#[test]
+fn reports_operations_not_supported_by_the_schema() {
+    let documents = "subscription { viewer }";
+
+    let stats = schema_stats(CUSTOM_ROOTS_SCHEMA, documents, &StatsOptions::default()).unwrap();
+
+    assert_eq!(
+        stats.errors,
+        vec!["The schema doesn't define a subscription root type".to_string()]
+    );
+}
+
+#[test]
+fn returns_an_error_for_undefined_root_types() {
+    let result = schema_stats(
+        "schema { query: Missing }\ntype Query { viewer: String }",
+        "{ viewer }",
+        &StatsOptions::default(),
+    );
+
+    assert!(matches!(result, Err(Error::InvalidSchema(_))));
+}
+
+const ALL_KINDS_SCHEMA: &str = "
+scalar DateTime
+
+enum Role {
+  ADMIN
+  MEMBER
+}
+
+input UserFilter {
+  role: Role
+}
+
+type User {
+  name: String
+  role: Role
+  joinedAt: DateTime
+}
+
+type Team {
+  name: String
+}
+
+union SearchResult = User | Team
+
+type Query {
+  search(filter: UserFilter): [SearchResult!]!
+}
+";
+
+#[test]
+fn registers_every_type_kind() {
+    let documents = "{ search { ... on User { name joinedAt } ... on Team { name } } }\n\
+                     { search { ... on User { role } } }";
+
+    let stats = schema_stats(ALL_KINDS_SCHEMA, documents, &StatsOptions::default()).unwrap();
+
+    assert_eq!(stats.types["SearchResult"].kind, TypeKind::Union);
+    assert_eq!(stats.types["SearchResult"].count, 2);
+    assert_eq!(stats.types["SearchResult"].members["User"], 2);
+    assert_eq!(stats.types["SearchResult"].members["Team"], 1);
+    assert_eq!(stats.types["DateTime"].kind, TypeKind::Scalar);
+    assert_eq!(stats.types["DateTime"].count, 1);
+    assert_eq!(stats.types["Role"].kind, TypeKind::Enum);
+    assert_eq!(stats.types["Role"].values.len(), 2);
+    assert_eq!(stats.types["UserFilter"].kind, TypeKind::InputObject);
+    assert!(stats.types["UserFilter"].fields.contains_key("role"));
+    assert!(stats.errors.is_empty());
+}
+
+#[test]
+fn tags_types_with_their_kind_in_json() {
+    let stats = schema_stats(
+        ALL_KINDS_SCHEMA,
+        "{ search { __typename } }",
+        &Default::default(),
+    )
+    .unwrap();
+
+    let json = serde_json::to_value(&stats.types).unwrap();
+
+    assert_eq!(json["Role"]["kind"], "enum");
+    assert_eq!(json["UserFilter"]["kind"], "input_object");
+    assert_eq!(json["Role"]["values"]["ADMIN"], 0);
+}
+
+#[test]
+fn counts_field_arguments() {
+    let schema = "
+enum Role {
+  ADMIN
+  MEMBER
+}
+
+type User {
+  name: String
+}
+
+type Query {
+  users(first: Int = 10, after: String, roles: [Role!], active: Boolean): [User!]!
+}
+";
+    let documents = "{ users(first: 5, roles: [ADMIN, MEMBER], active: true) { name } }\n\
+                     query Users($first: Int) { users(first: $first, roles: [ADMIN]) { name } }";
+
+    let stats = schema_stats(schema, documents, &StatsOptions::default()).unwrap();
+    let arguments = &stats.types["Query"].fields["users"].arguments;
+
+    assert_eq!(arguments["first"].count, 2);
+    assert_eq!(arguments["first"].literal, 1);
+    assert_eq!(arguments["first"].variable, 1);
+    assert_eq!(arguments["first"].default_value.as_deref(), Some("10"));
+    assert!(arguments["first"].values.is_empty());
+    assert_eq!(arguments["roles"].values["ADMIN"], 2);
+    assert_eq!(arguments["roles"].values["MEMBER"], 1);
+    assert_eq!(arguments["active"].values["true"], 1);
+    assert_eq!(arguments["after"].count, 0);
+    assert_eq!(stats.unused_arguments(), vec!["Query.users(after:)"]);
+}
+
+#[test]
+fn reports_unknown_arguments() {
+    let stats = schema_stats(
+        &schema(),
+        "{ user(id: 1, name: \"ada\") { id } viewer { name } }",
+        &StatsOptions::default(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        stats.errors,
+        vec!["Unable to get argument name of Query.user"]
+    );
+
+    // the rest of the operation is still counted
+    assert_eq!(stats.types["Query"].fields["user"].arguments["id"].count, 1);
+    assert_eq!(count(&stats, "User", "id"), 1);
+    assert_eq!(count(&stats, "Query", "viewer"), 1);
+    assert_eq!(count(&stats, "User", "name"), 1);
+}
+
+#[test]
+fn accepts_object_literals_for_custom_scalars() {
+    let schema = "
+scalar JSON
+
+type User {
+  name: String
+}
+
+type Query {
+  users(where: JSON): [User!]!
+}
+";
+    let documents = "{ users(where: { name: { eq: \"ada\" } }) { name } }";
+
+    let stats = schema_stats(schema, documents, &StatsOptions::default()).unwrap();
+
+    assert_eq!(
+        stats.types["Query"].fields["users"].arguments["where"].literal,
+        1
+    );
+    assert_eq!(stats.types["User"].fields["name"].count, 1);
+    assert!(stats.errors.is_empty());
+}
+
+#[test]
+fn counts_input_fields_and_enum_values() {
+    let schema = "
+enum Role {
+  ADMIN
+  MEMBER
+  GUEST
+}
+
+input Range {
+  from: Int
+  to: Int
+}
+
+input UserFilter {
+  role: Role
+  name: String
+  joined: Range
+}
+
+type User {
+  name: String
+}
+
+type Query {
+  users(filter: UserFilter): [User!]!
+}
+";
+    let documents = "{ users(filter: { role: ADMIN, joined: { from: 1 } }) { name } }\n\
+                     query Users($filter: UserFilter) { users(filter: $filter) { name } }\n\
+                     query Guests($role: Role = GUEST) { users(filter: { role: $role }) { name } }";
+
+    let stats = schema_stats(schema, documents, &StatsOptions::default()).unwrap();
+    let filter = &stats.types["UserFilter"];
+
+    assert_eq!(filter.fields["role"].count, 2);
+    assert_eq!(filter.fields["joined"].count, 1);
+    assert_eq!(filter.fields["name"].count, 0);
+    assert_eq!(filter.fields["name"].via_variable, 1);
+    assert_eq!(stats.types["Range"].fields["from"].count, 1);
+    assert_eq!(stats.types["Range"].fields["to"].count, 0);
+    assert_eq!(stats.types["Range"].fields["to"].via_variable, 1);
+
+    let role = &stats.types["Role"];
+    assert_eq!(role.values["ADMIN"], 1);
+    assert_eq!(role.values["GUEST"], 1);
+    assert_eq!(role.values["MEMBER"], 0);
+    assert_eq!(role.values_via_variable["MEMBER"], 2);
+    assert!(stats.errors.is_empty());
+}
+
+#[test]
+fn counts_directives() {
+    let schema = "
+directive @cached(seconds: Int) on QUERY | FIELD
+directive @featureFlag(name: String!) on FIELD | INLINE_FRAGMENT
+directive @key(fields: String!) on OBJECT
+
+type User @key(fields: \"id\") {
+  id: ID!
+  name: String
+}
+
+type Query {
+  viewer: User
+}
+";
+    let documents = "query Viewer($withName: Boolean!) @cached(seconds: 60) {\n\
+                       viewer { id name @include(if: $withName) ...UserName }\n\
+                     }\n\
+                     fragment UserName on User { name @connection(key: \"name\") }\n\
+                     { viewer @cached(seconds: 60) { ... @include(if: false) { id } } }";
+
+    let stats = schema_stats(schema, documents, &StatsOptions::default()).unwrap();
+
+    let cached = &stats.directives["cached"];
+    assert_eq!(cached.count, 2);
+    assert_eq!(cached.locations["QUERY"], 1);
+    assert_eq!(cached.locations["FIELD"], 1);
+    assert_eq!(cached.arguments["seconds"]["60"], 2);
+
+    let include = &stats.directives["include"];
+    assert_eq!(include.count, 2);
+    assert_eq!(include.locations["FIELD"], 1);
+    assert_eq!(include.locations["INLINE_FRAGMENT"], 1);
+    assert_eq!(include.arguments["if"]["$withName"], 1);
+    assert_eq!(include.arguments["if"]["false"], 1);
+
+    assert!(!stats.directives["connection"].defined);
+    assert_eq!(stats.undefined_directives(), vec!["connection"]);
+    assert_eq!(stats.unused_directives(), vec!["featureFlag", "skip"]);
+}
+
+#[test]
+fn counts_fragment_definition_directives_once() {
+    let schema = "
+directive @cached(seconds: Int) on FRAGMENT_DEFINITION | FRAGMENT_SPREAD
+
+type User {
+  id: ID!
+  name: String
+}
+
+type Query {
+  viewer: User
+  user(id: ID!): User
+}
+";
+    let documents =
+        "query { viewer { ...UserName @cached(seconds: 10) } user(id: 1) { ...UserName } }\n\
+                     fragment UserName on User @cached(seconds: 60) { name }";
+
+    for include_fragments in &[false, true] {
+        let options = StatsOptions {
+            include_fragments: *include_fragments,
+            ..Default::default()
+        };
+
+        let stats = schema_stats(schema, documents, &options).unwrap();
+
+        let cached = &stats.directives["cached"];
+        assert_eq!(cached.count, 2);
+        assert_eq!(cached.locations["FRAGMENT_DEFINITION"], 1);
+        assert_eq!(cached.locations["FRAGMENT_SPREAD"], 1);
+        assert_eq!(cached.arguments["seconds"]["60"], 1);
+        assert_eq!(cached.arguments["seconds"]["10"], 1);
+    }
+}
+
+#[test]
+fn attributes_fields_to_operations_fragments_and_files() {
+    let documents = "# pal: kind=javascript; path=src/Viewer.js\n\
+                     query Viewer { viewer { id ...UserName } }\n\
+                     # pal: kind=graphql; path=src/user.graphql\n\
+                     fragment UserName on User { name }\n\
+                     query User { user(id: 1) { id } }";
+
+    let options = StatsOptions {
+        attribution: true,
+        ..Default::default()
+    };
+    let stats = schema_stats(&schema(), documents, &options).unwrap();
+
+    let id = &stats.types["User"].fields["id"].used_by;
+    assert_eq!(
+        id.operations.iter().collect::<Vec<_>>(),
+        vec!["User", "Viewer"]
+    );
+    assert!(id.fragments.is_empty());
+    assert_eq!(
+        id.files.iter().collect::<Vec<_>>(),
+        vec!["src/Viewer.js", "src/user.graphql"]
+    );
+
+    let name = &stats.types["User"].fields["name"].used_by;
+    assert_eq!(name.operations.iter().collect::<Vec<_>>(), vec!["Viewer"]);
+    assert_eq!(name.fragments.iter().collect::<Vec<_>>(), vec!["UserName"]);
+    assert_eq!(
+        name.files.iter().collect::<Vec<_>>(),
+        vec!["src/user.graphql"]
+    );
+
+    let json = serde_json::to_value(&stats).unwrap();
+    assert_eq!(
+        json["types"]["User"]["fields"]["name"]["used_by"]["fragments"][0],
+        "UserName"
+    );
+
+    let stats = schema_stats(&schema(), documents, &StatsOptions::default()).unwrap();
+    assert!(stats.types["User"].fields["name"].used_by.is_empty());
+}
+
+#[test]
+fn counts_distinct_operations_and_aliases() {
+    let documents = "fragment UserName on User { name }
+query Profile { viewer { ...UserName name displayName: name } }
+query Friends { viewer { friends { ...UserName } } }";
+
+    let stats = schema_stats(&schema(), documents, &StatsOptions::default()).unwrap();
+
+    let name = &stats.types["User"].fields["name"];
+    assert_eq!(name.count, 4);
+    assert_eq!(name.operations, 2);
+    assert_eq!(name.aliases["displayName"], 1);
+    assert_eq!(name.aliases.len(), 1);
+
+    let viewer = &stats.types["Query"].fields["viewer"];
+    assert_eq!(viewer.count, 2);
+    assert_eq!(viewer.operations, 2);
+    assert!(viewer.aliases.is_empty());
+
+    // the standalone fragment pass adds selections, not operations
+    let options = StatsOptions {
+        include_fragments: true,
+        ..Default::default()
+    };
+    let stats = schema_stats(&schema(), documents, &options).unwrap();
+
+    let name = &stats.types["User"].fields["name"];
+    assert_eq!(name.count, 5);
+    assert_eq!(name.operations, 2);
+}
+
+#[test]
+fn builds_a_usage_tree_of_paths() {
+    let documents = "fragment Friends on User { friends { name } }
+query { viewer { ...Friends } }
+query { user(id: 1) { name ...Friends } }
+query { user(id: 2) { friends { id } } }";
+
+    let options = StatsOptions {
+        tree: true,
+        ..Default::default()
+    };
+    let stats = schema_stats(&schema(), documents, &options).unwrap();
+
+    let query = &stats.tree["Query"];
+    assert_eq!(query.count, 3);
+
+    let viewer = &query.children["viewer"];
+    assert_eq!(viewer.r#type, "User");
+    assert_eq!(viewer.children["friends"].children["name"].count, 1);
+    assert!(!viewer.children.contains_key("name"));
+
+    let user = &query.children["user"];
+    assert_eq!(user.count, 2);
+    assert_eq!(user.children["name"].count, 1);
+    assert_eq!(user.children["friends"].count, 2);
+    assert_eq!(user.children["friends"].children["id"].count, 1);
+
+    // the flat counts mix both paths
+    assert_eq!(count(&stats, "User", "name"), 3);
+
+    let pruned = query.pruned(Some(1), 2);
+    assert_eq!(pruned.children.keys().collect::<Vec<_>>(), vec!["user"]);
+    assert!(pruned.children["user"].children.is_empty());
+
+    let stats = schema_stats(&schema(), documents, &StatsOptions::default()).unwrap();
+    assert!(stats.tree.is_empty());
+}
+
+#[test]
+fn keys_the_usage_tree_by_response_path() {
+    let documents = "query { a: user(id: 1) { name } b: user(id: 2) { email } user(id: 3) { id } }";
+
+    let options = StatsOptions {
+        tree: true,
+        ..Default::default()
+    };
+    let stats = schema_stats(&schema(), documents, &options).unwrap();
+
+    let query = &stats.tree["Query"];
+    assert_eq!(
+        query.children.keys().collect::<Vec<_>>(),
+        vec!["a", "b", "user"]
+    );
+    assert_eq!(query.children["a"].r#type, "User");
+    assert_eq!(
+        query.children["a"].children.keys().collect::<Vec<_>>(),
+        vec!["name"]
+    );
+    assert_eq!(
+        query.children["b"].children.keys().collect::<Vec<_>>(),
+        vec!["email"]
+    );
+    assert_eq!(
+        query.children["user"].children.keys().collect::<Vec<_>>(),
+        vec!["id"]
+    );
+
+    // the flat counts are still per field
+    assert_eq!(count(&stats, "Query", "user"), 3);
+}
+
+#[test]
+fn merges_type_extensions() {
+    let schema = "
+extend type Query {
+  team: Team
+}
+
+type Query {
+  viewer: User
+}
+
+type User {
+  name: String
+}
+
+type Team {
+  name: String
+}
+
+extend union Result = Team
+
+union Result = User
+
+extend enum Role { ADMIN }
+
+enum Role { MEMBER }
+";
+    let documents = "{ viewer { name } team { name } }";
+
+    let stats = schema_stats(schema, documents, &StatsOptions::default()).unwrap();
+
+    assert_eq!(count(&stats, "Query", "team"), 1);
+    assert_eq!(count(&stats, "Team", "name"), 1);
+    assert_eq!(stats.types["Result"].members.len(), 2);
+    assert_eq!(stats.types["Role"].values.len(), 2);
+    assert!(stats.errors.is_empty());
+}
+
+#[test]
+fn returns_an_error_for_extensions_of_undefined_types() {
+    let result = schema_stats(
+        "type Query { viewer: String }\nextend type User { name: String }",
+        "{ viewer }",
+        &StatsOptions::default(),
+    );
+
+    match result {
+        Err(Error::InvalidSchema(reason)) => {
+            assert_eq!(reason, "Unable to extend undefined type User at 2:8")
+        }
+        _ => panic!("expected an invalid schema"),
+    }
+}
+
+#[test]
+fn returns_an_error_for_redefined_fields() {
+    let result = schema_stats(
+        "type Query { viewer: String }\nextend type Query { viewer: Int }",
+        "{ viewer }",
+        &StatsOptions::default(),
+    );
+
+    match result {
+        Err(Error::InvalidSchema(reason)) => {
+            assert!(reason.starts_with("Field Query.viewer is already defined"))
+        }
+        _ => panic!("expected an invalid schema"),
+    }
+}